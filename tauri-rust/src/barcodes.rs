@@ -0,0 +1,241 @@
+use sqlx::PgConnection;
+use tauri::State;
+
+use crate::database::Database;
+use crate::images;
+use crate::menu::{MenuItemData, MENU_ITEM_COLUMNS};
+use crate::menu_history::{self, MenuItemSnapshot};
+use crate::pricing;
+
+/// EAN-13 prefix reserved by GS1 for in-store numbering, so generated codes
+/// never clash with a manufacturer's barcode.
+const INTERNAL_PREFIX: &str = "20";
+
+// ── Helpers ──
+
+fn check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { d })
+        .sum();
+
+    (10 - sum % 10) % 10
+}
+
+/// Validates an EAN-13 or UPC-A code and returns it as EAN-13 (UPC-A gets a
+/// leading zero), so both scan forms of the same product match.
+pub(crate) fn normalize_barcode(code: &str) -> Result<String, String> {
+    let code = code.trim();
+    if !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("บาร์โค้ดต้องเป็นตัวเลขเท่านั้น".to_string());
+    }
+
+    let ean = match code.len() {
+        13 => code.to_string(),
+        12 => format!("0{}", code),
+        _ => return Err("บาร์โค้ดต้องเป็น EAN-13 (13 หลัก) หรือ UPC-A (12 หลัก)".to_string()),
+    };
+
+    let (body, check) = ean.split_at(12);
+    if check.parse::<u32>().ok() != Some(check_digit(body)) {
+        return Err("เลขตรวจสอบของบาร์โค้ดไม่ถูกต้อง".to_string());
+    }
+
+    Ok(ean)
+}
+
+/// In-store EAN-13 code for a sequence number: the internal prefix, the
+/// number padded to ten digits, and the check digit.
+fn internal_barcode(seq: i64) -> String {
+    let body = format!("{}{:010}", INTERNAL_PREFIX, seq);
+    format!("{}{}", body, check_digit(&body))
+}
+
+async fn next_internal_barcode(conn: &mut PgConnection) -> Result<String, sqlx::Error> {
+    loop {
+        let (seq,): (i64,) = sqlx::query_as("SELECT nextval('internal_barcode_seq')")
+            .fetch_one(&mut *conn)
+            .await?;
+        let code = internal_barcode(seq);
+
+        let taken: Option<(i32,)> = sqlx::query_as("SELECT id FROM menu_items WHERE barcode = $1")
+            .bind(&code)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if taken.is_none() {
+            return Ok(code);
+        }
+    }
+}
+
+// ── Commands ──
+
+/// Lookup for USB scanner input. Returns the item with its current price.
+#[tauri::command]
+pub async fn find_item_by_barcode(
+    code: String,
+    db: State<'_, Database>,
+) -> Result<Option<MenuItemData>, String> {
+    let code = normalize_barcode(&code)?;
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let item = sqlx::query_as::<_, MenuItemData>(&format!(
+        "SELECT {} FROM menu_items WHERE barcode = $1 AND is_available = TRUE",
+        MENU_ITEM_COLUMNS
+    ))
+    .bind(&code)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(mut item) = item else {
+        return Ok(None);
+    };
+    let prices = pricing::active_prices(&mut conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    item.base_price = item.price;
+    item.price = prices.price_for(item.id, &item.category_id, item.base_price);
+    item.image_url = images::image_url(&item.image);
+
+    Ok(Some(item))
+}
+
+/// Sets or clears an item's barcode and SKU. With `generate` set and no
+/// barcode given, an internal EAN-13 code is assigned.
+#[tauri::command]
+pub async fn set_menu_item_barcode(
+    id: i32,
+    barcode: Option<String>,
+    sku: Option<String>,
+    generate: Option<bool>,
+    changed_by: String,
+    db: State<'_, Database>,
+) -> Result<Option<String>, String> {
+    let barcode = barcode
+        .as_deref()
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(normalize_barcode)
+        .transpose()?;
+    let sku = sku
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from);
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(before) = menu_history::snapshot(&mut tx, id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Err("ไม่พบเมนู".to_string());
+    };
+
+    let barcode = match barcode {
+        None if generate.unwrap_or(false) => Some(
+            next_internal_barcode(&mut tx)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        other => other,
+    };
+
+    sqlx::query("UPDATE menu_items SET barcode = $1, sku = $2 WHERE id = $3")
+        .bind(&barcode)
+        .bind(&sku)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+            Some(c) if c == "23505" => "บาร์โค้ดหรือ SKU นี้ใช้กับเมนูอื่นแล้ว".to_string(),
+            _ => format!("บันทึกบาร์โค้ดไม่สำเร็จ: {}", e),
+        })?;
+
+    let after = MenuItemSnapshot {
+        barcode: barcode.clone(),
+        sku,
+        ..before.clone()
+    };
+    menu_history::record_changes(&mut tx, id, &before, &after, &changed_by)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(barcode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digit_of_known_codes() {
+        assert_eq!(check_digit("400638133393"), 1);
+        assert_eq!(check_digit("590123412345"), 7);
+        assert_eq!(check_digit("885000000000"), 3);
+    }
+
+    #[test]
+    fn accepts_valid_ean13() {
+        assert_eq!(
+            normalize_barcode("4006381333931"),
+            Ok("4006381333931".to_string())
+        );
+        assert_eq!(
+            normalize_barcode(" 5901234123457 "),
+            Ok("5901234123457".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_check_digit() {
+        assert_eq!(
+            normalize_barcode("4006381333932"),
+            Err("เลขตรวจสอบของบาร์โค้ดไม่ถูกต้อง".to_string())
+        );
+        assert_eq!(
+            normalize_barcode("036000291453"),
+            Err("เลขตรวจสอบของบาร์โค้ดไม่ถูกต้อง".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_length_and_letters() {
+        assert!(normalize_barcode("40063813339").is_err());
+        assert!(normalize_barcode("40063813339311").is_err());
+        assert!(normalize_barcode("").is_err());
+        assert_eq!(
+            normalize_barcode("40063813339A1"),
+            Err("บาร์โค้ดต้องเป็นตัวเลขเท่านั้น".to_string())
+        );
+    }
+
+    #[test]
+    fn upc_a_becomes_ean13() {
+        assert_eq!(
+            normalize_barcode("036000291452"),
+            Ok("0036000291452".to_string())
+        );
+        assert_eq!(
+            normalize_barcode("036000291452"),
+            normalize_barcode("0036000291452")
+        );
+    }
+
+    #[test]
+    fn generated_codes_are_valid_internal_ean13() {
+        for seq in [1, 42, 9_999_999_999] {
+            let code = internal_barcode(seq);
+            assert_eq!(code.len(), 13);
+            assert!(code.starts_with(INTERNAL_PREFIX));
+            assert_eq!(normalize_barcode(&code), Ok(code.clone()));
+        }
+        assert_eq!(internal_barcode(1), "2000000000015");
+    }
+}
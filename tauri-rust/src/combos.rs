@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::orders::OrderItemInput;
+
+// ── Request / Response types ──
+
+/// A set sold at one price, e.g. "coffee + croissant 99". Each slot is filled
+/// with one available item from its category.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Combo {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub price: f64,
+    pub is_active: bool,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub slots: Vec<ComboSlot>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ComboSlot {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub category_id: String,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub upcharges: Vec<ComboUpcharge>,
+}
+
+/// Extra charge for choosing a particular item in a slot, e.g. +15 for a
+/// large latte. Items without an entry are included at no extra cost.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ComboUpcharge {
+    pub menu_item_id: i32,
+    pub upcharge: f64,
+}
+
+/// One combo on an order: `item_ids` holds the chosen item for each slot, in
+/// slot order.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ComboOrderInput {
+    pub combo_id: i32,
+    pub qty: i32,
+    pub item_ids: Vec<i32>,
+}
+
+#[derive(FromRow)]
+struct UpchargeRow {
+    combo_slot_id: i32,
+    #[sqlx(flatten)]
+    upcharge: ComboUpcharge,
+}
+
+// ── Helpers ──
+
+async fn load_combos(
+    conn: &mut PgConnection,
+    combo_id: Option<i32>,
+) -> Result<Vec<Combo>, sqlx::Error> {
+    let mut combos = sqlx::query_as::<_, Combo>(
+        "SELECT id, name, price, is_active
+         FROM combos
+         WHERE $1::integer IS NULL OR id = $1
+         ORDER BY is_active DESC, id",
+    )
+    .bind(combo_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for combo in &mut combos {
+        combo.slots = sqlx::query_as::<_, ComboSlot>(
+            "SELECT id, name, category_id FROM combo_slots WHERE combo_id = $1 ORDER BY sort_order",
+        )
+        .bind(combo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let slot_ids: Vec<i32> = combo.slots.iter().map(|s| s.id).collect();
+        let upcharges = sqlx::query_as::<_, UpchargeRow>(
+            "SELECT combo_slot_id, menu_item_id, upcharge
+             FROM combo_slot_upcharges
+             WHERE combo_slot_id = ANY($1)
+             ORDER BY menu_item_id",
+        )
+        .bind(&slot_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut by_slot: HashMap<i32, Vec<ComboUpcharge>> = HashMap::new();
+        for row in upcharges {
+            by_slot
+                .entry(row.combo_slot_id)
+                .or_default()
+                .push(row.upcharge);
+        }
+        for slot in &mut combo.slots {
+            slot.upcharges = by_slot.remove(&slot.id).unwrap_or_default();
+        }
+    }
+
+    Ok(combos)
+}
+
+/// Expands combos into component order lines. The combo price is split over
+/// the components in proportion to their menu prices, and each upcharge is
+/// added to the line it belongs to, so item reports and stock still work per
+/// component.
+pub(crate) async fn expand(
+    conn: &mut PgConnection,
+    selections: &[ComboOrderInput],
+) -> Result<Vec<OrderItemInput>, String> {
+    let mut lines = Vec::new();
+
+    for (index, selection) in selections.iter().enumerate() {
+        if selection.qty <= 0 {
+            return Err("จำนวนชุดต้องมากกว่า 0".to_string());
+        }
+
+        let combo = load_combos(&mut *conn, Some(selection.combo_id))
+            .await
+            .map_err(|e| e.to_string())?
+            .pop()
+            .ok_or_else(|| "ไม่พบชุดเมนู".to_string())?;
+        if !combo.is_active {
+            return Err(format!("ชุด {} ถูกปิดใช้งานแล้ว", combo.name));
+        }
+        if selection.item_ids.len() != combo.slots.len() {
+            return Err(format!("กรุณาเลือกเมนูให้ครบทุกตัวเลือกของชุด {}", combo.name));
+        }
+
+        let items: HashMap<i32, (String, String, f64, bool)> =
+            sqlx::query_as::<_, (i32, String, String, f64, bool)>(
+                "SELECT id, name, category_id, price, is_available
+                 FROM menu_items WHERE id = ANY($1)",
+            )
+            .bind(&selection.item_ids)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(id, name, category_id, price, available)| {
+                (id, (name, category_id, price, available))
+            })
+            .collect();
+
+        let mut chosen = Vec::new();
+        for (slot, item_id) in combo.slots.iter().zip(&selection.item_ids) {
+            let (name, category_id, base_price, available) = items
+                .get(item_id)
+                .ok_or_else(|| format!("ไม่พบเมนูที่เลือกใน {}", slot.name))?;
+            if category_id != &slot.category_id {
+                return Err(format!("{} เลือกใน {} ไม่ได้", name, slot.name));
+            }
+            if !available {
+                return Err(format!("{} หมดชั่วคราว", name));
+            }
+            let upcharge = slot
+                .upcharges
+                .iter()
+                .find(|u| u.menu_item_id == *item_id)
+                .map_or(0.0, |u| u.upcharge);
+            chosen.push((*item_id, name.clone(), *base_price, upcharge));
+        }
+
+        let base_total: f64 = chosen.iter().map(|c| c.2).sum();
+        let mut allocated = 0.0;
+        let count = chosen.len();
+        for (i, (item_id, item_name, base_price, upcharge)) in chosen.into_iter().enumerate() {
+            let share = if i + 1 == count {
+                ((combo.price - allocated) * 100.0).round() / 100.0
+            } else if base_total > 0.0 {
+                (combo.price * base_price / base_total * 100.0).round() / 100.0
+            } else {
+                (combo.price / count as f64 * 100.0).round() / 100.0
+            };
+            allocated += share;
+
+            lines.push(OrderItemInput {
+                item_id,
+                item_name,
+                qty: f64::from(selection.qty),
+                price: share + upcharge,
+                is_reward: false,
+                combo_id: Some(combo.id),
+                combo_index: Some(index as i32),
+                sold_by: "unit".to_string(),
+            });
+        }
+    }
+
+    Ok(lines)
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_combos(db: State<'_, Database>) -> Result<Vec<Combo>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    load_combos(&mut conn, None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Creates a combo when `id` is 0, otherwise replaces it and its slots.
+#[tauri::command]
+pub async fn save_combo(combo: Combo, db: State<'_, Database>) -> Result<i32, String> {
+    if combo.price < 0.0 {
+        return Err("ราคาชุดต้องไม่ติดลบ".to_string());
+    }
+    if combo.slots.is_empty() {
+        return Err("ชุดเมนูต้องมีอย่างน้อย 1 ตัวเลือก".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let id = if combo.id == 0 {
+        let (id,): (i32,) = sqlx::query_as(
+            "INSERT INTO combos (name, price, is_active) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(&combo.name)
+        .bind(combo.price)
+        .bind(combo.is_active)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกชุดเมนูไม่สำเร็จ: {}", e))?;
+        id
+    } else {
+        let rows =
+            sqlx::query("UPDATE combos SET name = $1, price = $2, is_active = $3 WHERE id = $4")
+                .bind(&combo.name)
+                .bind(combo.price)
+                .bind(combo.is_active)
+                .bind(combo.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("บันทึกชุดเมนูไม่สำเร็จ: {}", e))?
+                .rows_affected();
+        if rows == 0 {
+            return Err("ไม่พบชุดเมนู".to_string());
+        }
+
+        sqlx::query(
+            "DELETE FROM combo_slot_upcharges
+             WHERE combo_slot_id IN (SELECT id FROM combo_slots WHERE combo_id = $1)",
+        )
+        .bind(combo.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query("DELETE FROM combo_slots WHERE combo_id = $1")
+            .bind(combo.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        combo.id
+    };
+
+    for (sort_order, slot) in combo.slots.iter().enumerate() {
+        let (slot_id,): (i32,) = sqlx::query_as(
+            "INSERT INTO combo_slots (combo_id, name, category_id, sort_order)
+             VALUES ($1, $2, $3, $4)
+             RETURNING id",
+        )
+        .bind(id)
+        .bind(&slot.name)
+        .bind(&slot.category_id)
+        .bind(sort_order as i32)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกชุดเมนูไม่สำเร็จ: {}", e))?;
+
+        for upcharge in &slot.upcharges {
+            sqlx::query(
+                "INSERT INTO combo_slot_upcharges (combo_slot_id, menu_item_id, upcharge)
+                 VALUES ($1, $2, $3)",
+            )
+            .bind(slot_id)
+            .bind(upcharge.menu_item_id)
+            .bind(upcharge.upcharge)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("บันทึกชุดเมนูไม่สำเร็จ: {}", e))?;
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+/// Combos stay in the database because past orders reference them.
+#[tauri::command]
+pub async fn deactivate_combo(id: i32, db: State<'_, Database>) -> Result<bool, String> {
+    let rows = sqlx::query("UPDATE combos SET is_active = FALSE WHERE id = $1")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+
+    Ok(rows > 0)
+}
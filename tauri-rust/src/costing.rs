@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Deserialize)]
+pub struct RecipeLineInput {
+    pub stock_item_id: i32,
+    pub qty: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct RecipeLine {
+    pub stock_item_id: i32,
+    pub stock_item_name: String,
+    pub unit: String,
+    pub qty: f64,
+    pub cost_per_unit: f64,
+    pub line_cost: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct MenuItemCost {
+    pub id: i32,
+    pub name: String,
+    pub category_id: String,
+    pub price: f64,
+    pub cost: f64,
+    #[sqlx(skip)]
+    pub margin_pct: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct MarginRow {
+    pub key: String,
+    pub name: String,
+    /// Pieces, plus kg or 100 g units for weighed items.
+    pub qty_sold: f64,
+    pub revenue: f64,
+    pub cost: f64,
+    #[sqlx(skip)]
+    pub margin_pct: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarginReport {
+    pub start_date: String,
+    pub end_date: String,
+    pub items: Vec<MarginRow>,
+    pub categories: Vec<MarginRow>,
+}
+
+// ── Helpers ──
+
+fn margin_pct(revenue: f64, cost: f64) -> f64 {
+    if revenue > 0.0 {
+        (revenue - cost) / revenue * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Current recipe cost of one unit of a menu item, priced at the latest
+/// ingredient costs. Items without a recipe cost 0.
+pub(crate) async fn unit_cost(
+    conn: &mut PgConnection,
+    menu_item_id: i32,
+) -> Result<f64, sqlx::Error> {
+    let (cost,): (f64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(r.qty * si.cost_per_unit), 0)
+         FROM recipes r
+         JOIN stock_items si ON si.id = r.stock_item_id
+         WHERE r.menu_item_id = $1",
+    )
+    .bind(menu_item_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(cost)
+}
+
+/// Ingredient lines of a menu item's recipe as
+/// `(stock_item_id, qty per unit, current cost per unit)`.
+pub(crate) async fn recipe_lines(
+    conn: &mut PgConnection,
+    menu_item_id: i32,
+) -> Result<Vec<(i32, f64, f64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT r.stock_item_id, r.qty, si.cost_per_unit
+         FROM recipes r
+         JOIN stock_items si ON si.id = r.stock_item_id
+         WHERE r.menu_item_id = $1",
+    )
+    .bind(menu_item_id)
+    .fetch_all(conn)
+    .await
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_recipe(
+    menu_item_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<RecipeLine>, String> {
+    sqlx::query_as::<_, RecipeLine>(
+        "SELECT r.stock_item_id, si.name AS stock_item_name, si.unit, r.qty, si.cost_per_unit,
+                (r.qty * si.cost_per_unit) AS line_cost
+         FROM recipes r
+         JOIN stock_items si ON si.id = r.stock_item_id
+         WHERE r.menu_item_id = $1
+         ORDER BY si.name",
+    )
+    .bind(menu_item_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Replaces the whole recipe of a menu item.
+#[tauri::command]
+pub async fn set_recipe(
+    menu_item_id: i32,
+    lines: Vec<RecipeLineInput>,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if lines.iter().any(|l| l.qty <= 0.0) {
+        return Err("ปริมาณวัตถุดิบต้องมากกว่า 0".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM recipes WHERE menu_item_id = $1")
+        .bind(menu_item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for line in &lines {
+        sqlx::query(
+            "INSERT INTO recipes (menu_item_id, stock_item_id, qty)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (menu_item_id, stock_item_id) DO UPDATE SET qty = recipes.qty + EXCLUDED.qty",
+        )
+        .bind(menu_item_id)
+        .bind(line.stock_item_id)
+        .bind(line.qty)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกสูตรไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn get_menu_item_costs(db: State<'_, Database>) -> Result<Vec<MenuItemCost>, String> {
+    let mut rows = sqlx::query_as::<_, MenuItemCost>(
+        "SELECT m.id, m.name, m.category_id, m.price,
+                COALESCE(SUM(r.qty * si.cost_per_unit), 0) AS cost
+         FROM menu_items m
+         LEFT JOIN recipes r ON r.menu_item_id = m.id
+         LEFT JOIN stock_items si ON si.id = r.stock_item_id
+         WHERE m.is_available = TRUE
+         GROUP BY m.id
+         ORDER BY m.category_id, m.id",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for row in &mut rows {
+        row.margin_pct = margin_pct(row.price, row.cost);
+    }
+
+    Ok(rows)
+}
+
+/// Margin by item and category over a date range, using the unit costs
+/// snapshotted on `order_items` at sale time.
+#[tauri::command]
+pub async fn get_margin_report(
+    start_date: String,
+    end_date: String,
+    db: State<'_, Database>,
+) -> Result<MarginReport, String> {
+    let mut items = sqlx::query_as::<_, MarginRow>(
+        "SELECT oi.item_id::text AS key, MAX(oi.item_name) AS name,
+                COALESCE(SUM(oi.qty), 0) AS qty_sold,
+                COALESCE(SUM(oi.qty * oi.price), 0) AS revenue,
+                COALESCE(SUM(oi.qty * oi.unit_cost), 0) AS cost
+         FROM order_items oi
+         JOIN orders o ON o.id = oi.order_id
+         WHERE o.created_at::date BETWEEN $1::date AND $2::date
+         GROUP BY oi.item_id
+         ORDER BY revenue DESC",
+    )
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut categories = sqlx::query_as::<_, MarginRow>(
+        "SELECT COALESCE(c.id, '') AS key, COALESCE(MAX(c.name), 'ไม่ระบุหมวดหมู่') AS name,
+                COALESCE(SUM(oi.qty), 0) AS qty_sold,
+                COALESCE(SUM(oi.qty * oi.price), 0) AS revenue,
+                COALESCE(SUM(oi.qty * oi.unit_cost), 0) AS cost
+         FROM order_items oi
+         JOIN orders o ON o.id = oi.order_id
+         LEFT JOIN menu_items m ON m.id = oi.item_id
+         LEFT JOIN categories c ON c.id = m.category_id
+         WHERE o.created_at::date BETWEEN $1::date AND $2::date
+         GROUP BY c.id
+         ORDER BY revenue DESC",
+    )
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for row in items.iter_mut().chain(categories.iter_mut()) {
+        row.margin_pct = margin_pct(row.revenue, row.cost);
+    }
+
+    Ok(MarginReport {
+        start_date,
+        end_date,
+        items,
+        categories,
+    })
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::menu;
+use crate::orders::OrderItemInput;
+
+// ── Request / Response types ──
+
+/// `applicable_categories` / `applicable_items` limit which lines the discount
+/// applies to; when both are empty the whole order is eligible. Usage limits
+/// are `None` when unlimited.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Coupon {
+    pub code: String,
+    pub description: String,
+    pub discount_type: String,
+    pub value: f64,
+    pub min_spend: f64,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub applicable_categories: Vec<String>,
+    pub applicable_items: Vec<i32>,
+    pub max_uses: Option<i32>,
+    pub max_uses_per_customer: Option<i32>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CouponCheck {
+    pub valid: bool,
+    pub code: String,
+    pub discount: f64,
+    pub error: Option<String>,
+}
+
+// ── Helpers ──
+
+const COUPON_COLUMNS: &str = "code, description, discount_type, value, min_spend, valid_from,
+    valid_until, applicable_categories, applicable_items, max_uses, max_uses_per_customer,
+    is_active";
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Checks a coupon against the cart and returns `(code, discount)`.
+///
+/// With `lock` set the coupon row is locked `FOR UPDATE`, so inside
+/// `create_order`'s transaction two tills cannot both take the last use.
+pub(crate) async fn evaluate(
+    conn: &mut PgConnection,
+    code: &str,
+    items: &[OrderItemInput],
+    customer_id: Option<i32>,
+    lock: bool,
+) -> Result<(String, f64), String> {
+    let code = normalize_code(code);
+
+    let coupon = sqlx::query_as::<_, Coupon>(&format!(
+        "SELECT {} FROM coupons WHERE code = $1{}",
+        COUPON_COLUMNS,
+        if lock { " FOR UPDATE" } else { "" }
+    ))
+    .bind(&code)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "ไม่พบคูปองนี้".to_string())?;
+
+    if !coupon.is_active {
+        return Err("คูปองนี้ถูกปิดใช้งานแล้ว".to_string());
+    }
+
+    let now = Utc::now();
+    if coupon.valid_from.is_some_and(|from| now < from) {
+        return Err("คูปองนี้ยังไม่เริ่มใช้งาน".to_string());
+    }
+    if coupon.valid_until.is_some_and(|until| now > until) {
+        return Err("คูปองนี้หมดอายุแล้ว".to_string());
+    }
+
+    if let Some(max_uses) = coupon.max_uses {
+        let (used,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*)::bigint FROM coupon_redemptions WHERE code = $1")
+                .bind(&code)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        if used >= i64::from(max_uses) {
+            return Err("คูปองนี้ถูกใช้ครบจำนวนแล้ว".to_string());
+        }
+    }
+
+    if let Some(max_per_customer) = coupon.max_uses_per_customer {
+        let customer_id = customer_id.ok_or_else(|| "คูปองนี้ต้องระบุลูกค้าสมาชิก".to_string())?;
+        let (used,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*)::bigint FROM coupon_redemptions WHERE code = $1 AND customer_id = $2",
+        )
+        .bind(&code)
+        .bind(customer_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        if used >= i64::from(max_per_customer) {
+            return Err("ลูกค้าใช้คูปองนี้ครบสิทธิ์แล้ว".to_string());
+        }
+    }
+
+    let paid: Vec<&OrderItemInput> = items.iter().filter(|i| !i.is_reward).collect();
+    let subtotal: f64 = paid.iter().map(|i| i.price * i.qty).sum();
+    if subtotal < coupon.min_spend {
+        return Err(format!("ยอดซื้อขั้นต่ำ {:.2} บาท", coupon.min_spend));
+    }
+
+    let eligible = if coupon.applicable_categories.is_empty() && coupon.applicable_items.is_empty()
+    {
+        subtotal
+    } else {
+        let item_ids: Vec<i32> = paid.iter().map(|i| i.item_id).collect();
+        let categories = menu::item_categories(&mut *conn, &item_ids)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        paid.iter()
+            .filter(|i| {
+                coupon.applicable_items.contains(&i.item_id)
+                    || categories
+                        .get(&i.item_id)
+                        .is_some_and(|c| coupon.applicable_categories.contains(c))
+            })
+            .map(|i| i.price * i.qty)
+            .sum()
+    };
+
+    if eligible <= 0.0 {
+        return Err("ไม่มีสินค้าที่ร่วมรายการกับคูปองนี้".to_string());
+    }
+
+    let discount = match coupon.discount_type.as_str() {
+        "percent" => eligible * coupon.value / 100.0,
+        _ => coupon.value.min(eligible),
+    };
+
+    Ok((code, (discount * 100.0).round() / 100.0))
+}
+
+pub(crate) async fn record_redemption(
+    conn: &mut PgConnection,
+    code: &str,
+    order_id: &str,
+    customer_id: Option<i32>,
+    discount: f64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO coupon_redemptions (code, order_id, customer_id, discount)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(code)
+    .bind(order_id)
+    .bind(customer_id)
+    .bind(discount)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_coupons(db: State<'_, Database>) -> Result<Vec<Coupon>, String> {
+    sqlx::query_as::<_, Coupon>(&format!(
+        "SELECT {} FROM coupons ORDER BY is_active DESC, code",
+        COUPON_COLUMNS
+    ))
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_coupon(
+    coupon: Coupon,
+    created_by: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if coupon.discount_type != "percent" && coupon.discount_type != "fixed" {
+        return Err("ประเภทส่วนลดต้องเป็น percent หรือ fixed".to_string());
+    }
+    if coupon.value <= 0.0 || (coupon.discount_type == "percent" && coupon.value > 100.0) {
+        return Err("มูลค่าส่วนลดไม่ถูกต้อง".to_string());
+    }
+
+    sqlx::query(
+        "INSERT INTO coupons (code, description, discount_type, value, min_spend, valid_from,
+                              valid_until, applicable_categories, applicable_items, max_uses,
+                              max_uses_per_customer, is_active, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+    )
+    .bind(normalize_code(&coupon.code))
+    .bind(&coupon.description)
+    .bind(&coupon.discount_type)
+    .bind(coupon.value)
+    .bind(coupon.min_spend)
+    .bind(coupon.valid_from)
+    .bind(coupon.valid_until)
+    .bind(&coupon.applicable_categories)
+    .bind(&coupon.applicable_items)
+    .bind(coupon.max_uses)
+    .bind(coupon.max_uses_per_customer)
+    .bind(coupon.is_active)
+    .bind(&created_by)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+        Some(c) if c == "23505" => "รหัสคูปองนี้มีอยู่แล้ว".to_string(),
+        _ => format!("สร้างคูปองไม่สำเร็จ: {}", e),
+    })?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn deactivate_coupon(code: String, db: State<'_, Database>) -> Result<bool, String> {
+    let rows = sqlx::query("UPDATE coupons SET is_active = FALSE WHERE code = $1")
+        .bind(normalize_code(&code))
+        .execute(&db.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+
+    Ok(rows > 0)
+}
+
+/// Preview for the checkout screen. The result is not reserved; usage limits
+/// are checked again when the order is created.
+#[tauri::command]
+pub async fn validate_coupon(
+    code: String,
+    items: Vec<OrderItemInput>,
+    customer_id: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<CouponCheck, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    Ok(
+        match evaluate(&mut conn, &code, &items, customer_id, false).await {
+            Ok((code, discount)) => CouponCheck {
+                valid: true,
+                code,
+                discount,
+                error: None,
+            },
+            Err(e) => CouponCheck {
+                valid: false,
+                code: normalize_code(&code),
+                discount: 0.0,
+                error: Some(e),
+            },
+        },
+    )
+}
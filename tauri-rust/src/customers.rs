@@ -0,0 +1,284 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Customer {
+    pub id: i32,
+    pub name: String,
+    pub phone: String,
+    pub birthday: Option<NaiveDate>,
+    pub pdpa_consent: bool,
+    pub consent_at: Option<DateTime<Utc>>,
+    pub points_balance: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCustomer {
+    pub name: String,
+    pub phone: String,
+    pub birthday: Option<NaiveDate>,
+    pub pdpa_consent: bool,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PointsEntry {
+    pub id: i32,
+    pub order_id: Option<String>,
+    pub points: i32,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Earn rule: one point for every `baht_per_point` spent. Redeem rule: each
+/// point is worth `point_value` baht off, from `min_redeem_points` upwards.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct LoyaltyConfig {
+    pub baht_per_point: f64,
+    pub point_value: f64,
+    pub min_redeem_points: i32,
+}
+
+// ── Helpers ──
+
+const CUSTOMER_COLUMNS: &str = "c.id, c.name, c.phone, c.birthday, c.pdpa_consent, c.consent_at,
+    COALESCE((SELECT SUM(points) FROM points_ledger WHERE customer_id = c.id), 0)::bigint AS points_balance,
+    c.created_at";
+
+/// Keeps only digits so "081-234-5678" and "0812345678" find the same customer.
+fn normalize_phone(phone: &str) -> String {
+    phone.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+pub(crate) async fn loyalty_config(conn: &mut PgConnection) -> Result<LoyaltyConfig, sqlx::Error> {
+    sqlx::query_as::<_, LoyaltyConfig>(
+        "SELECT baht_per_point, point_value, min_redeem_points FROM loyalty_config WHERE id = 1",
+    )
+    .fetch_one(conn)
+    .await
+}
+
+pub(crate) fn points_earned(config: &LoyaltyConfig, amount: f64) -> i32 {
+    if config.baht_per_point <= 0.0 || amount <= 0.0 {
+        return 0;
+    }
+    (amount / config.baht_per_point).floor() as i32
+}
+
+/// Locks the customer row and checks that `points` can be redeemed.
+/// Returns the discount in baht.
+pub(crate) async fn redemption_discount(
+    conn: &mut PgConnection,
+    config: &LoyaltyConfig,
+    customer_id: i32,
+    points: i32,
+) -> Result<f64, String> {
+    if points < 0 {
+        return Err("จำนวนแต้มที่ใช้ต้องไม่ติดลบ".to_string());
+    }
+    if points == 0 {
+        return Ok(0.0);
+    }
+    if points < config.min_redeem_points {
+        return Err(format!("ต้องใช้แต้มขั้นต่ำ {} แต้ม", config.min_redeem_points));
+    }
+
+    let locked: Option<(i32,)> =
+        sqlx::query_as("SELECT id FROM customers WHERE id = $1 FOR UPDATE")
+            .bind(customer_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    if locked.is_none() {
+        return Err("ไม่พบข้อมูลลูกค้า".to_string());
+    }
+
+    let (balance,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(points), 0)::bigint FROM points_ledger WHERE customer_id = $1",
+    )
+    .bind(customer_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if i64::from(points) > balance {
+        return Err(format!("แต้มไม่พอ (คงเหลือ {} แต้ม)", balance));
+    }
+
+    Ok(f64::from(points) * config.point_value)
+}
+
+pub(crate) async fn add_points(
+    conn: &mut PgConnection,
+    customer_id: i32,
+    order_id: Option<&str>,
+    points: i32,
+    reason: &str,
+    created_by: &str,
+) -> Result<(), sqlx::Error> {
+    if points == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT INTO points_ledger (customer_id, order_id, points, reason, created_by)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(customer_id)
+    .bind(order_id)
+    .bind(points)
+    .bind(reason)
+    .bind(created_by)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn add_customer(
+    customer: NewCustomer,
+    db: State<'_, Database>,
+) -> Result<Customer, String> {
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO customers (name, phone, birthday, pdpa_consent, consent_at)
+         VALUES ($1, $2, $3, $4, CASE WHEN $4 THEN CURRENT_TIMESTAMP END)
+         RETURNING id",
+    )
+    .bind(&customer.name)
+    .bind(normalize_phone(&customer.phone))
+    .bind(customer.birthday)
+    .bind(customer.pdpa_consent)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+        Some(code) if code == "23505" => "เบอร์โทรนี้มีในระบบแล้ว".to_string(),
+        _ => format!("เพิ่มลูกค้าไม่สำเร็จ: {}", e),
+    })?;
+
+    sqlx::query_as::<_, Customer>(&format!(
+        "SELECT {} FROM customers c WHERE c.id = $1",
+        CUSTOMER_COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_customer(
+    id: i32,
+    customer: NewCustomer,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "UPDATE customers
+         SET name = $1, phone = $2, birthday = $3, pdpa_consent = $4,
+             consent_at = CASE
+                 WHEN $4 AND NOT pdpa_consent THEN CURRENT_TIMESTAMP
+                 WHEN $4 THEN consent_at
+             END
+         WHERE id = $5",
+    )
+    .bind(&customer.name)
+    .bind(normalize_phone(&customer.phone))
+    .bind(customer.birthday)
+    .bind(customer.pdpa_consent)
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("อัปเดตข้อมูลลูกค้าไม่สำเร็จ: {}", e))?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
+
+#[tauri::command]
+pub async fn find_customer_by_phone(
+    phone: String,
+    db: State<'_, Database>,
+) -> Result<Option<Customer>, String> {
+    sqlx::query_as::<_, Customer>(&format!(
+        "SELECT {} FROM customers c WHERE c.phone = $1",
+        CUSTOMER_COLUMNS
+    ))
+    .bind(normalize_phone(&phone))
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_points_history(
+    customer_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<PointsEntry>, String> {
+    sqlx::query_as::<_, PointsEntry>(
+        "SELECT id, order_id, points, reason, created_by, created_at
+         FROM points_ledger
+         WHERE customer_id = $1
+         ORDER BY created_at DESC, id DESC",
+    )
+    .bind(customer_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Manual correction by an admin; the ledger keeps it as an `adjust` entry.
+#[tauri::command]
+pub async fn adjust_points(
+    customer_id: i32,
+    points: i32,
+    user_id: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    add_points(&mut conn, customer_id, None, points, "adjust", &user_id)
+        .await
+        .map_err(|e| format!("ปรับแต้มไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn get_loyalty_config(db: State<'_, Database>) -> Result<LoyaltyConfig, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    loyalty_config(&mut conn).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_loyalty_config(
+    config: LoyaltyConfig,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if config.baht_per_point < 0.0 || config.point_value < 0.0 || config.min_redeem_points < 0 {
+        return Err("ค่าการตั้งค่าแต้มต้องไม่ติดลบ".to_string());
+    }
+
+    sqlx::query(
+        "UPDATE loyalty_config
+         SET baht_per_point = $1, point_value = $2, min_redeem_points = $3
+         WHERE id = 1",
+    )
+    .bind(config.baht_per_point)
+    .bind(config.point_value)
+    .bind(config.min_redeem_points)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกการตั้งค่าแต้มไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
@@ -85,6 +85,838 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query(
+            "ALTER TABLE order_items
+             ADD COLUMN IF NOT EXISTS unit_cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+             ADD COLUMN IF NOT EXISTS is_reward BOOLEAN NOT NULL DEFAULT FALSE",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stock_items (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                unit TEXT NOT NULL,
+                qty DOUBLE PRECISION NOT NULL DEFAULT 0,
+                cost_per_unit DOUBLE PRECISION NOT NULL DEFAULT 0,
+                reorder_level DOUBLE PRECISION NOT NULL DEFAULT 0,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stock_movements (
+                id SERIAL PRIMARY KEY,
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                qty_change DOUBLE PRECISION NOT NULL,
+                reason TEXT NOT NULL,
+                reference TEXT NOT NULL DEFAULT '',
+                unit_cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS suppliers (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                contact_name TEXT NOT NULL DEFAULT '',
+                phone TEXT NOT NULL DEFAULT '',
+                note TEXT NOT NULL DEFAULT '',
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS purchase_orders (
+                id SERIAL PRIMARY KEY,
+                supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+                status TEXT NOT NULL DEFAULT 'open'
+                    CHECK(status IN ('open', 'partial', 'received', 'cancelled')),
+                note TEXT NOT NULL DEFAULT '',
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                received_at TIMESTAMPTZ
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS purchase_order_lines (
+                id SERIAL PRIMARY KEY,
+                po_id INTEGER NOT NULL REFERENCES purchase_orders(id),
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                qty_ordered DOUBLE PRECISION NOT NULL,
+                qty_received DOUBLE PRECISION NOT NULL DEFAULT 0,
+                unit_cost DOUBLE PRECISION NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS goods_receipts (
+                id SERIAL PRIMARY KEY,
+                po_line_id INTEGER NOT NULL REFERENCES purchase_order_lines(id),
+                qty DOUBLE PRECISION NOT NULL,
+                unit_cost DOUBLE PRECISION NOT NULL,
+                received_by TEXT NOT NULL,
+                received_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS supplier_prices (
+                supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                last_price DOUBLE PRECISION NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (supplier_id, stock_item_id)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS recipes (
+                menu_item_id INTEGER NOT NULL REFERENCES menu_items(id),
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                qty DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (menu_item_id, stock_item_id)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS waste_log (
+                id SERIAL PRIMARY KEY,
+                stock_item_id INTEGER REFERENCES stock_items(id),
+                menu_item_id INTEGER REFERENCES menu_items(id),
+                item_name TEXT NOT NULL,
+                qty DOUBLE PRECISION NOT NULL,
+                reason TEXT NOT NULL
+                    CHECK(reason IN ('expired', 'spilled', 'damaged', 'mistake', 'other')),
+                note TEXT NOT NULL DEFAULT '',
+                total_cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stocktakes (
+                id SERIAL PRIMARY KEY,
+                status TEXT NOT NULL DEFAULT 'open'
+                    CHECK(status IN ('open', 'closed', 'cancelled')),
+                note TEXT NOT NULL DEFAULT '',
+                opened_by TEXT NOT NULL,
+                opened_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                closed_by TEXT,
+                closed_at TIMESTAMPTZ
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS stocktakes_single_open
+             ON stocktakes (status) WHERE status = 'open'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stocktake_counts (
+                stocktake_id INTEGER NOT NULL REFERENCES stocktakes(id),
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                location TEXT NOT NULL DEFAULT '',
+                counted_qty DOUBLE PRECISION NOT NULL,
+                counted_by TEXT NOT NULL,
+                counted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (stocktake_id, stock_item_id, location)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stocktake_results (
+                stocktake_id INTEGER NOT NULL REFERENCES stocktakes(id),
+                stock_item_id INTEGER NOT NULL REFERENCES stock_items(id),
+                system_qty DOUBLE PRECISION NOT NULL,
+                counted_qty DOUBLE PRECISION NOT NULL,
+                unit_cost DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (stocktake_id, stock_item_id)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS customers (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                phone TEXT NOT NULL UNIQUE,
+                birthday DATE,
+                pdpa_consent BOOLEAN NOT NULL DEFAULT FALSE,
+                consent_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE orders
+             ADD COLUMN IF NOT EXISTS customer_id INTEGER REFERENCES customers(id),
+             ADD COLUMN IF NOT EXISTS discount DOUBLE PRECISION NOT NULL DEFAULT 0,
+             ADD COLUMN IF NOT EXISTS gift_card_amount DOUBLE PRECISION NOT NULL DEFAULT 0",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS points_ledger (
+                id SERIAL PRIMARY KEY,
+                customer_id INTEGER NOT NULL REFERENCES customers(id),
+                order_id TEXT REFERENCES orders(id),
+                points INTEGER NOT NULL,
+                reason TEXT NOT NULL CHECK(reason IN ('earn', 'redeem', 'adjust')),
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS loyalty_config (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                baht_per_point DOUBLE PRECISION NOT NULL,
+                point_value DOUBLE PRECISION NOT NULL,
+                min_redeem_points INTEGER NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Default rule: 1 point per 25 baht, 1 point = 1 baht off, redeem from 50 points
+        sqlx::query(
+            "INSERT INTO loyalty_config (id, baht_per_point, point_value, min_redeem_points)
+             VALUES (1, 25, 1, 50)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stamp_config (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                qualifying_categories TEXT[] NOT NULL,
+                stamps_required INTEGER NOT NULL CHECK(stamps_required > 0),
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Default card: buy 9 coffees, the 10th is free
+        sqlx::query(
+            "INSERT INTO stamp_config (id, qualifying_categories, stamps_required)
+             VALUES (1, '{coffee}', 9)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS stamp_ledger (
+                id SERIAL PRIMARY KEY,
+                customer_id INTEGER NOT NULL REFERENCES customers(id),
+                order_id TEXT REFERENCES orders(id),
+                stamps INTEGER NOT NULL,
+                reason TEXT NOT NULL CHECK(reason IN ('earn', 'redeem', 'adjust')),
+                note TEXT NOT NULL DEFAULT '',
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS gift_cards (
+                code TEXT PRIMARY KEY,
+                initial_balance DOUBLE PRECISION NOT NULL,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS gift_card_transactions (
+                id SERIAL PRIMARY KEY,
+                code TEXT NOT NULL REFERENCES gift_cards(code),
+                order_id TEXT REFERENCES orders(id),
+                amount DOUBLE PRECISION NOT NULL,
+                kind TEXT NOT NULL CHECK(kind IN ('issue', 'redeem', 'adjust')),
+                payment_method TEXT,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS coupons (
+                code TEXT PRIMARY KEY,
+                description TEXT NOT NULL DEFAULT '',
+                discount_type TEXT NOT NULL CHECK(discount_type IN ('percent', 'fixed')),
+                value DOUBLE PRECISION NOT NULL,
+                min_spend DOUBLE PRECISION NOT NULL DEFAULT 0,
+                valid_from TIMESTAMPTZ,
+                valid_until TIMESTAMPTZ,
+                applicable_categories TEXT[] NOT NULL DEFAULT '{}',
+                applicable_items INTEGER[] NOT NULL DEFAULT '{}',
+                max_uses INTEGER,
+                max_uses_per_customer INTEGER,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS coupon_redemptions (
+                id SERIAL PRIMARY KEY,
+                code TEXT NOT NULL REFERENCES coupons(code),
+                order_id TEXT NOT NULL REFERENCES orders(id),
+                customer_id INTEGER REFERENCES customers(id),
+                discount DOUBLE PRECISION NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS price_lists (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                priority INTEGER NOT NULL DEFAULT 0,
+                weekdays INTEGER[] NOT NULL DEFAULT '{}',
+                start_time TIME,
+                end_time TIME,
+                start_date DATE,
+                end_date DATE,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS price_list_entries (
+                id SERIAL PRIMARY KEY,
+                price_list_id INTEGER NOT NULL REFERENCES price_lists(id),
+                menu_item_id INTEGER REFERENCES menu_items(id),
+                category_id TEXT REFERENCES categories(id),
+                percent_off DOUBLE PRECISION,
+                fixed_price DOUBLE PRECISION,
+                CHECK((menu_item_id IS NULL) <> (category_id IS NULL)),
+                CHECK((percent_off IS NULL) <> (fixed_price IS NULL))
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS combos (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS combo_slots (
+                id SERIAL PRIMARY KEY,
+                combo_id INTEGER NOT NULL REFERENCES combos(id),
+                name TEXT NOT NULL,
+                category_id TEXT NOT NULL REFERENCES categories(id),
+                sort_order INTEGER NOT NULL DEFAULT 0
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS combo_slot_upcharges (
+                combo_slot_id INTEGER NOT NULL REFERENCES combo_slots(id),
+                menu_item_id INTEGER NOT NULL REFERENCES menu_items(id),
+                upcharge DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (combo_slot_id, menu_item_id)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE order_items
+             ADD COLUMN IF NOT EXISTS combo_id INTEGER REFERENCES combos(id),
+             ADD COLUMN IF NOT EXISTS combo_index INTEGER",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE categories
+             ADD COLUMN IF NOT EXISTS is_archived BOOLEAN NOT NULL DEFAULT FALSE",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE menu_items ADD COLUMN IF NOT EXISTS sku TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS menu_items_sku
+             ON menu_items (sku) WHERE sku IS NOT NULL",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS menu_item_history (
+                id SERIAL PRIMARY KEY,
+                menu_item_id INTEGER NOT NULL REFERENCES menu_items(id),
+                field TEXT NOT NULL,
+                old_value TEXT NOT NULL,
+                new_value TEXT NOT NULL,
+                changed_by TEXT NOT NULL,
+                changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS scheduled_price_changes (
+                id SERIAL PRIMARY KEY,
+                menu_item_id INTEGER NOT NULL REFERENCES menu_items(id),
+                new_price DOUBLE PRECISION NOT NULL,
+                effective_date DATE NOT NULL,
+                created_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                applied_at TIMESTAMPTZ,
+                is_cancelled BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS menu_images (
+                hash TEXT PRIMARY KEY,
+                file_name TEXT NOT NULL,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                size_bytes BIGINT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE menu_items
+             ADD COLUMN IF NOT EXISTS allergens TEXT[] NOT NULL DEFAULT '{}',
+             ADD COLUMN IF NOT EXISTS dietary_flags TEXT[] NOT NULL DEFAULT '{}',
+             ADD COLUMN IF NOT EXISTS calories INTEGER",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS print_config (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                show_dietary_info BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("INSERT INTO print_config (id) VALUES (1) ON CONFLICT DO NOTHING")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS translations (
+                entity_type TEXT NOT NULL CHECK(entity_type IN ('category', 'menu_item')),
+                entity_id TEXT NOT NULL,
+                locale TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                PRIMARY KEY (entity_type, entity_id, locale)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE menu_items ADD COLUMN IF NOT EXISTS barcode TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS menu_items_barcode
+             ON menu_items (barcode) WHERE barcode IS NOT NULL",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE SEQUENCE IF NOT EXISTS internal_barcode_seq")
+            .execute(&self.pool)
+            .await?;
+
+        // Weighed items: `price` is per kg or per 100 g, and `order_items.qty`
+        // holds the weight in that unit.
+        sqlx::query(
+            "ALTER TABLE menu_items
+             ADD COLUMN IF NOT EXISTS sold_by TEXT NOT NULL DEFAULT 'unit'
+                 CHECK(sold_by IN ('unit', 'kg', '100g'))",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "DO $$
+             BEGIN
+                 IF EXISTS (
+                     SELECT 1 FROM information_schema.columns
+                     WHERE table_name = 'order_items' AND column_name = 'qty'
+                       AND data_type = 'integer'
+                 ) THEN
+                     ALTER TABLE order_items ALTER COLUMN qty TYPE DOUBLE PRECISION;
+                 END IF;
+             END $$",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE order_items ADD COLUMN IF NOT EXISTS sold_by TEXT NOT NULL DEFAULT 'unit'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS held_orders (
+                id SERIAL PRIMARY KEY,
+                label TEXT NOT NULL,
+                customer_id INTEGER REFERENCES customers(id),
+                coupon_code TEXT,
+                cashier_id TEXT NOT NULL,
+                cashier_name TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS held_order_items (
+                id SERIAL PRIMARY KEY,
+                held_order_id INTEGER NOT NULL REFERENCES held_orders(id) ON DELETE CASCADE,
+                item_id INTEGER NOT NULL,
+                item_name TEXT NOT NULL,
+                qty DOUBLE PRECISION NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                is_reward BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS held_order_combos (
+                id SERIAL PRIMARY KEY,
+                held_order_id INTEGER NOT NULL REFERENCES held_orders(id) ON DELETE CASCADE,
+                combo_id INTEGER NOT NULL REFERENCES combos(id),
+                qty INTEGER NOT NULL,
+                item_ids INTEGER[] NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS dining_zones (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                sort_order INTEGER NOT NULL DEFAULT 0
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS dining_tables (
+                id SERIAL PRIMARY KEY,
+                zone_id INTEGER NOT NULL REFERENCES dining_zones(id),
+                name TEXT NOT NULL,
+                seats INTEGER NOT NULL DEFAULT 2,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tabs (
+                id SERIAL PRIMARY KEY,
+                table_id INTEGER NOT NULL REFERENCES dining_tables(id),
+                guests INTEGER NOT NULL DEFAULT 1,
+                status TEXT NOT NULL DEFAULT 'open'
+                    CHECK(status IN ('open', 'settled', 'merged')),
+                split_ways INTEGER,
+                shares_paid INTEGER NOT NULL DEFAULT 0,
+                merged_into INTEGER REFERENCES tabs(id),
+                opened_by TEXT NOT NULL,
+                opened_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                closed_at TIMESTAMPTZ
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tab_items (
+                id SERIAL PRIMARY KEY,
+                tab_id INTEGER NOT NULL REFERENCES tabs(id),
+                item_id INTEGER NOT NULL,
+                item_name TEXT NOT NULL,
+                qty DOUBLE PRECISION NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                sold_by TEXT NOT NULL DEFAULT 'unit',
+                combo_id INTEGER REFERENCES combos(id),
+                combo_index INTEGER,
+                added_by TEXT NOT NULL,
+                added_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE orders
+             ADD COLUMN IF NOT EXISTS order_type TEXT NOT NULL DEFAULT 'takeaway'
+                 CHECK(order_type IN ('dine_in', 'takeaway', 'delivery')),
+             ADD COLUMN IF NOT EXISTS table_id INTEGER REFERENCES dining_tables(id),
+             ADD COLUMN IF NOT EXISTS tab_id INTEGER REFERENCES tabs(id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Kitchen/bar progress. Rows that existed before tracking count as
+        // served so they never show up in the queue; new rows start queued.
+        for table in ["orders", "order_items"] {
+            sqlx::query(&format!(
+                "ALTER TABLE {}
+                 ADD COLUMN IF NOT EXISTS kitchen_status TEXT NOT NULL DEFAULT 'served'
+                     CHECK(kitchen_status IN ('queued', 'preparing', 'ready', 'served')),
+                 ADD COLUMN IF NOT EXISTS preparing_at TIMESTAMPTZ,
+                 ADD COLUMN IF NOT EXISTS ready_at TIMESTAMPTZ,
+                 ADD COLUMN IF NOT EXISTS served_at TIMESTAMPTZ",
+                table
+            ))
+            .execute(&self.pool)
+            .await?;
+
+            sqlx::query(&format!(
+                "ALTER TABLE {} ALTER COLUMN kitchen_status SET DEFAULT 'queued'",
+                table
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS orders_kitchen_queue
+             ON orders (created_at) WHERE kitchen_status <> 'served'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE orders ADD COLUMN IF NOT EXISTS note TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS printers (
+                id SERIAL PRIMARY KEY,
+                name TEXT NOT NULL,
+                role TEXT NOT NULL CHECK(role IN ('receipt', 'kitchen', 'bar', 'label')),
+                connection TEXT NOT NULL DEFAULT 'network'
+                    CHECK(connection IN ('network', 'cups')),
+                host TEXT,
+                port INTEGER,
+                queue_name TEXT,
+                thai_codepage INTEGER,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE printers
+             ADD COLUMN IF NOT EXISTS label_language TEXT NOT NULL DEFAULT 'tspl'
+                 CHECK(label_language IN ('tspl', 'zpl')),
+             ADD COLUMN IF NOT EXISTS label_width_mm INTEGER NOT NULL DEFAULT 40,
+             ADD COLUMN IF NOT EXISTS label_height_mm INTEGER NOT NULL DEFAULT 30",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Which printers get a category's items. Categories without a row
+        // go to every kitchen printer.
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS category_printers (
+                category_id TEXT NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+                printer_id INTEGER NOT NULL REFERENCES printers(id) ON DELETE CASCADE,
+                PRIMARY KEY (category_id, printer_id)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS print_jobs (
+                id SERIAL PRIMARY KEY,
+                printer_id INTEGER REFERENCES printers(id) ON DELETE CASCADE,
+                host TEXT,
+                port INTEGER,
+                kind TEXT NOT NULL CHECK(kind IN ('receipt', 'ticket', 'label', 'test')),
+                order_id TEXT REFERENCES orders(id) ON DELETE SET NULL,
+                payload BYTEA NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued'
+                    CHECK(status IN ('queued', 'sent', 'failed', 'cancelled')),
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                sent_at TIMESTAMPTZ,
+                CHECK(printer_id IS NOT NULL OR host IS NOT NULL)
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS print_jobs_due
+             ON print_jobs (next_attempt_at) WHERE status = 'queued'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Each round added to a tab goes to the kitchen on its own, numbered
+        // from the same daily sequence as orders. The order that settles the
+        // tab does not go to the kitchen again.
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tab_rounds (
+                id SERIAL PRIMARY KEY,
+                tab_id INTEGER NOT NULL REFERENCES tabs(id),
+                order_number INTEGER NOT NULL,
+                note TEXT,
+                kitchen_status TEXT NOT NULL DEFAULT 'queued'
+                    CHECK(kitchen_status IN ('queued', 'preparing', 'ready', 'served')),
+                preparing_at TIMESTAMPTZ,
+                ready_at TIMESTAMPTZ,
+                served_at TIMESTAMPTZ,
+                added_by TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS tab_rounds_kitchen_queue
+             ON tab_rounds (created_at) WHERE kitchen_status <> 'served'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Lines added before rounds existed never reach the kitchen queue.
+        sqlx::query(
+            "ALTER TABLE tab_items
+             ADD COLUMN IF NOT EXISTS round_id INTEGER REFERENCES tab_rounds(id),
+             ADD COLUMN IF NOT EXISTS kitchen_status TEXT NOT NULL DEFAULT 'served'
+                 CHECK(kitchen_status IN ('queued', 'preparing', 'ready', 'served')),
+             ADD COLUMN IF NOT EXISTS preparing_at TIMESTAMPTZ,
+             ADD COLUMN IF NOT EXISTS ready_at TIMESTAMPTZ,
+             ADD COLUMN IF NOT EXISTS served_at TIMESTAMPTZ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE tab_items ALTER COLUMN kitchen_status SET DEFAULT 'queued'")
+            .execute(&self.pool)
+            .await?;
+
+        // A line split across tabs is one kitchen line; the copy carries its
+        // timestamps but is left out of prep-time metrics.
+        sqlx::query(
+            "ALTER TABLE tab_items
+             ADD COLUMN IF NOT EXISTS split_from INTEGER
+                 REFERENCES tab_items(id) ON DELETE SET NULL",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // A stamp reward on a tab is free; the stamps are taken at settlement.
+        sqlx::query(
+            "ALTER TABLE tab_items ADD COLUMN IF NOT EXISTS is_reward BOOLEAN NOT NULL DEFAULT FALSE",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Uploaded images used to be saved as `menuimg://localhost/<file>`,
+        // which does not load on Windows; only the file name is kept now.
+        sqlx::query(
+            "UPDATE menu_items
+             SET image = regexp_replace(image, '^(menuimg://localhost/|https://menuimg\\.localhost/)', '')
+             WHERE image LIKE 'menuimg://localhost/%' OR image LIKE 'https://menuimg.localhost/%'",
+        )
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::menu_history::{self, MenuItemSnapshot};
+
+pub const ALLERGENS: [&str; 5] = ["milk", "nuts", "gluten", "soy", "egg"];
+pub const DIETARY_FLAGS: [&str; 2] = ["vegan", "caffeine_free"];
+
+// ── Request / Response types ──
+
+/// What to print alongside item lines on receipts and labels.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PrintConfig {
+    pub show_dietary_info: bool,
+}
+
+// ── Helpers ──
+
+/// Rejects tags outside the known lists, and returns them sorted and deduped.
+pub(crate) fn normalize_tags(
+    tags: &[String],
+    known: &[&str],
+    label: &str,
+) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase().replace('-', "_");
+        if !known.contains(&tag.as_str()) {
+            return Err(format!(
+                "{} '{}' ไม่ถูกต้อง (ใช้ได้: {})",
+                label,
+                tag,
+                known.join(", ")
+            ));
+        }
+        normalized.push(tag);
+    }
+    normalized.sort();
+    normalized.dedup();
+
+    Ok(normalized)
+}
+
+/// One-line summary for a receipt or label, e.g.
+/// `Contains: milk, nuts | vegan | 180 kcal`. Empty when there is nothing to say.
+pub(crate) fn dietary_note(
+    allergens: &[String],
+    dietary_flags: &[String],
+    calories: Option<i32>,
+) -> String {
+    let mut parts = Vec::new();
+    if !allergens.is_empty() {
+        parts.push(format!("Contains: {}", allergens.join(", ")));
+    }
+    if !dietary_flags.is_empty() {
+        parts.push(dietary_flags.join(", ").replace('_', "-"));
+    }
+    if let Some(kcal) = calories {
+        parts.push(format!("{} kcal", kcal));
+    }
+
+    parts.join(" | ")
+}
+
+pub(crate) async fn print_config(conn: &mut PgConnection) -> Result<PrintConfig, sqlx::Error> {
+    sqlx::query_as::<_, PrintConfig>("SELECT show_dietary_info FROM print_config WHERE id = 1")
+        .fetch_one(conn)
+        .await
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn set_menu_item_dietary(
+    id: i32,
+    allergens: Vec<String>,
+    dietary_flags: Vec<String>,
+    calories: Option<i32>,
+    changed_by: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let allergens = normalize_tags(&allergens, &ALLERGENS, "สารก่อภูมิแพ้")?;
+    let dietary_flags = normalize_tags(&dietary_flags, &DIETARY_FLAGS, "ข้อมูลโภชนาการ")?;
+    if calories.is_some_and(|c| c < 0) {
+        return Err("แคลอรี่ต้องไม่ติดลบ".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(before) = menu_history::snapshot(&mut tx, id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        "UPDATE menu_items SET allergens = $1, dietary_flags = $2, calories = $3 WHERE id = $4",
+    )
+    .bind(&allergens)
+    .bind(&dietary_flags)
+    .bind(calories)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("บันทึกข้อมูลสารก่อภูมิแพ้ไม่สำเร็จ: {}", e))?;
+
+    let after = MenuItemSnapshot {
+        allergens,
+        dietary_flags,
+        calories,
+        ..before.clone()
+    };
+    menu_history::record_changes(&mut tx, id, &before, &after, &changed_by)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn get_print_config(db: State<'_, Database>) -> Result<PrintConfig, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    print_config(&mut conn).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_print_config(
+    config: PrintConfig,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    sqlx::query("UPDATE print_config SET show_dietary_info = $1 WHERE id = 1")
+        .bind(config.show_dietary_info)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| format!("บันทึกการตั้งค่าการพิมพ์ไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
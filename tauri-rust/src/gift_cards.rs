@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+/// Part (or all) of an order paid from a gift card balance.
+#[derive(Debug, Deserialize)]
+pub struct GiftCardTender {
+    pub code: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct GiftCardBalance {
+    pub code: String,
+    pub initial_balance: f64,
+    pub balance: f64,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct GiftCardTransaction {
+    pub id: i32,
+    pub order_id: Option<String>,
+    pub amount: f64,
+    pub kind: String,
+    pub payment_method: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// ── Helpers ──
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+fn generate_code() -> String {
+    let raw = Uuid::new_v4().simple().to_string().to_uppercase();
+    format!("GC-{}-{}-{}", &raw[0..4], &raw[4..8], &raw[8..12])
+}
+
+/// Deducts `tender.amount` from a gift card for an order that has already
+/// been inserted in the same transaction.
+pub(crate) async fn redeem(
+    conn: &mut PgConnection,
+    tender: &GiftCardTender,
+    order_id: &str,
+    order_total: f64,
+    created_by: &str,
+) -> Result<(), String> {
+    if tender.amount <= 0.0 || tender.amount > order_total {
+        return Err("ยอดชำระด้วยบัตรของขวัญไม่ถูกต้อง".to_string());
+    }
+
+    let code = normalize_code(&tender.code);
+
+    let card: Option<(bool,)> =
+        sqlx::query_as("SELECT is_active FROM gift_cards WHERE code = $1 FOR UPDATE")
+            .bind(&code)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    match card {
+        Some((true,)) => {}
+        Some((false,)) => return Err("บัตรของขวัญนี้ถูกระงับแล้ว".to_string()),
+        None => return Err("ไม่พบบัตรของขวัญ".to_string()),
+    }
+
+    let (balance,): (f64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM gift_card_transactions WHERE code = $1",
+    )
+    .bind(&code)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if tender.amount > balance {
+        return Err(format!("ยอดในบัตรของขวัญไม่พอ (คงเหลือ {:.2} บาท)", balance));
+    }
+
+    sqlx::query(
+        "INSERT INTO gift_card_transactions (code, order_id, amount, kind, created_by)
+         VALUES ($1, $2, $3, 'redeem', $4)",
+    )
+    .bind(&code)
+    .bind(order_id)
+    .bind(-tender.amount)
+    .bind(created_by)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("ตัดยอดบัตรของขวัญไม่สำเร็จ: {}", e))?;
+
+    Ok(())
+}
+
+// ── Commands ──
+
+/// Sells a new gift card. The money taken is a liability until the card is
+/// redeemed, so it is not recorded as an order.
+#[tauri::command]
+pub async fn sell_gift_card(
+    code: Option<String>,
+    amount: f64,
+    payment_method: String,
+    cashier_id: String,
+    db: State<'_, Database>,
+) -> Result<GiftCardBalance, String> {
+    if amount <= 0.0 {
+        return Err("มูลค่าบัตรของขวัญต้องมากกว่า 0".to_string());
+    }
+
+    let code = code
+        .map(|c| normalize_code(&c))
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(generate_code);
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("INSERT INTO gift_cards (code, initial_balance, created_by) VALUES ($1, $2, $3)")
+        .bind(&code)
+        .bind(amount)
+        .bind(&cashier_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+            Some(c) if c == "23505" => "รหัสบัตรของขวัญนี้มีอยู่แล้ว".to_string(),
+            _ => format!("ขายบัตรของขวัญไม่สำเร็จ: {}", e),
+        })?;
+
+    sqlx::query(
+        "INSERT INTO gift_card_transactions (code, amount, kind, payment_method, created_by)
+         VALUES ($1, $2, 'issue', $3, $4)",
+    )
+    .bind(&code)
+    .bind(amount)
+    .bind(&payment_method)
+    .bind(&cashier_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("ขายบัตรของขวัญไม่สำเร็จ: {}", e))?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    check_gift_card_balance(code, db)
+        .await?
+        .ok_or_else(|| "ไม่พบบัตรของขวัญ".to_string())
+}
+
+#[tauri::command]
+pub async fn check_gift_card_balance(
+    code: String,
+    db: State<'_, Database>,
+) -> Result<Option<GiftCardBalance>, String> {
+    sqlx::query_as::<_, GiftCardBalance>(
+        "SELECT g.code, g.initial_balance,
+                COALESCE((SELECT SUM(amount) FROM gift_card_transactions WHERE code = g.code), 0) AS balance,
+                g.is_active, g.created_at
+         FROM gift_cards g
+         WHERE g.code = $1",
+    )
+    .bind(normalize_code(&code))
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_gift_card_transactions(
+    code: String,
+    db: State<'_, Database>,
+) -> Result<Vec<GiftCardTransaction>, String> {
+    sqlx::query_as::<_, GiftCardTransaction>(
+        "SELECT id, order_id, amount, kind, payment_method, created_by, created_at
+         FROM gift_card_transactions
+         WHERE code = $1
+         ORDER BY created_at DESC, id DESC",
+    )
+    .bind(normalize_code(&code))
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn deactivate_gift_card(code: String, db: State<'_, Database>) -> Result<bool, String> {
+    let rows = sqlx::query("UPDATE gift_cards SET is_active = FALSE WHERE code = $1")
+        .bind(normalize_code(&code))
+        .execute(&db.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+
+    Ok(rows > 0)
+}
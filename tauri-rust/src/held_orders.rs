@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::combos::ComboOrderInput;
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+/// A cart parked by a cashier, e.g. while a customer looks for their wallet.
+/// Held carts are shared by all terminals and expire at the end of the day.
+#[derive(Debug, Serialize, FromRow)]
+pub struct HeldOrder {
+    pub id: i32,
+    pub label: String,
+    pub customer_id: Option<i32>,
+    pub coupon_code: Option<String>,
+    pub cashier_id: String,
+    pub cashier_name: String,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub items: Vec<HeldItem>,
+    #[sqlx(skip)]
+    pub combos: Vec<ComboOrderInput>,
+}
+
+/// A cart line as the frontend sends it to `create_order`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct HeldItem {
+    pub item_id: i32,
+    pub item_name: String,
+    pub qty: f64,
+    pub price: f64,
+    #[serde(default)]
+    pub is_reward: bool,
+}
+
+#[derive(FromRow)]
+struct HeldItemRow {
+    held_order_id: i32,
+    #[sqlx(flatten)]
+    item: HeldItem,
+}
+
+#[derive(FromRow)]
+struct HeldComboRow {
+    held_order_id: i32,
+    #[sqlx(flatten)]
+    combo: ComboOrderInput,
+}
+
+// ── Helpers ──
+
+/// Drops carts held on earlier days.
+async fn delete_expired(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM held_orders WHERE created_at::date < $1")
+        .bind(Local::now().date_naive())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn load_lines(conn: &mut PgConnection, orders: &mut [HeldOrder]) -> Result<(), sqlx::Error> {
+    let ids: Vec<i32> = orders.iter().map(|o| o.id).collect();
+
+    let items = sqlx::query_as::<_, HeldItemRow>(
+        "SELECT held_order_id, item_id, item_name, qty, price, is_reward
+         FROM held_order_items
+         WHERE held_order_id = ANY($1)
+         ORDER BY id",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let combos = sqlx::query_as::<_, HeldComboRow>(
+        "SELECT held_order_id, combo_id, qty, item_ids
+         FROM held_order_combos
+         WHERE held_order_id = ANY($1)
+         ORDER BY id",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut items_by_order: HashMap<i32, Vec<HeldItem>> = HashMap::new();
+    for row in items {
+        items_by_order
+            .entry(row.held_order_id)
+            .or_default()
+            .push(row.item);
+    }
+    let mut combos_by_order: HashMap<i32, Vec<ComboOrderInput>> = HashMap::new();
+    for row in combos {
+        combos_by_order
+            .entry(row.held_order_id)
+            .or_default()
+            .push(row.combo);
+    }
+    for order in orders {
+        order.items = items_by_order.remove(&order.id).unwrap_or_default();
+        order.combos = combos_by_order.remove(&order.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+// ── Commands ──
+
+/// Saves the current cart under `label` so the cashier can clear the screen.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn hold_order(
+    label: String,
+    items: Vec<HeldItem>,
+    combos: Option<Vec<ComboOrderInput>>,
+    customer_id: Option<i32>,
+    coupon_code: Option<String>,
+    cashier_id: String,
+    cashier_name: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    let combos = combos.unwrap_or_default();
+    if items.is_empty() && combos.is_empty() {
+        return Err("ไม่มีรายการในตะกร้า".to_string());
+    }
+    let label = match label.trim() {
+        "" => format!("พักบิล {}", Local::now().format("%H:%M")),
+        label => label.to_string(),
+    };
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    delete_expired(&mut tx).await.map_err(|e| e.to_string())?;
+
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO held_orders (label, customer_id, coupon_code, cashier_id, cashier_name)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id",
+    )
+    .bind(&label)
+    .bind(customer_id)
+    .bind(coupon_code.as_deref().filter(|c| !c.trim().is_empty()))
+    .bind(&cashier_id)
+    .bind(&cashier_name)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("พักบิลไม่สำเร็จ: {}", e))?;
+
+    for item in &items {
+        sqlx::query(
+            "INSERT INTO held_order_items (held_order_id, item_id, item_name, qty, price, is_reward)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(id)
+        .bind(item.item_id)
+        .bind(&item.item_name)
+        .bind(item.qty)
+        .bind(item.price)
+        .bind(item.is_reward)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("พักบิลไม่สำเร็จ: {}", e))?;
+    }
+
+    for combo in &combos {
+        sqlx::query(
+            "INSERT INTO held_order_combos (held_order_id, combo_id, qty, item_ids)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(id)
+        .bind(combo.combo_id)
+        .bind(combo.qty)
+        .bind(&combo.item_ids)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("พักบิลไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+/// Today's held carts, oldest first.
+#[tauri::command]
+pub async fn list_held_orders(db: State<'_, Database>) -> Result<Vec<HeldOrder>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    delete_expired(&mut conn).await.map_err(|e| e.to_string())?;
+
+    let mut orders = sqlx::query_as::<_, HeldOrder>(
+        "SELECT id, label, customer_id, coupon_code, cashier_id, cashier_name, created_at
+         FROM held_orders
+         ORDER BY created_at",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    load_lines(&mut conn, &mut orders)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(orders)
+}
+
+/// Returns a held cart and removes it, so only one terminal can recall it.
+/// Prices are checked again by `create_order` at checkout.
+#[tauri::command]
+pub async fn recall_held_order(
+    id: i32,
+    db: State<'_, Database>,
+) -> Result<Option<HeldOrder>, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let order = sqlx::query_as::<_, HeldOrder>(
+        "SELECT id, label, customer_id, coupon_code, cashier_id, cashier_name, created_at
+         FROM held_orders
+         WHERE id = $1 AND created_at::date = $2
+         FOR UPDATE",
+    )
+    .bind(id)
+    .bind(Local::now().date_naive())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(order) = order else {
+        return Ok(None);
+    };
+    let mut orders = [order];
+    load_lines(&mut tx, &mut orders)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM held_orders WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let [order] = orders;
+    Ok(Some(order))
+}
@@ -0,0 +1,264 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageFormat;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, PgConnection};
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, Manager, State};
+
+use crate::database::Database;
+use crate::menu_history::{self, MenuItemSnapshot};
+
+/// Custom protocol that serves stored images, e.g. `menuimg://localhost/<file>`
+/// (`https://menuimg.localhost/<file>` on Windows).
+pub const PROTOCOL: &str = "menuimg";
+
+/// How the webview reaches `PROTOCOL`; WebView2 on Windows only loads custom
+/// protocols through `https://<protocol>.localhost/`.
+#[cfg(windows)]
+const URL_PREFIX: &str = "https://menuimg.localhost/";
+#[cfg(not(windows))]
+const URL_PREFIX: &str = "menuimg://localhost/";
+/// Both forms, for image URLs saved or exported on either platform.
+const PROTOCOL_PREFIXES: [&str; 2] = ["menuimg://localhost/", "https://menuimg.localhost/"];
+const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Directory under the app data dir that holds the image files. Managed as
+/// Tauri state from `main`.
+pub struct ImageStore {
+    pub dir: PathBuf,
+}
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize)]
+pub struct UploadedImage {
+    pub hash: String,
+    /// Value to save in `menu_items.image`: the file name only.
+    pub image: String,
+    /// URLs to show the image and its thumbnail on this platform.
+    pub url: String,
+    pub thumbnail_url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ── Helpers ──
+
+fn thumbnail_name(hash: &str) -> String {
+    format!("{}_thumb.jpg", hash)
+}
+
+/// What `menu_items.image` keeps for an image: a stored image by its file
+/// name, so the URL can be built for the platform reading it. Other URLs are
+/// kept as they are.
+pub(crate) fn stored_image(image: &str) -> String {
+    let image = image.trim();
+    PROTOCOL_PREFIXES
+        .iter()
+        .find_map(|prefix| image.strip_prefix(prefix))
+        .unwrap_or(image)
+        .to_string()
+}
+
+/// URL the webview can load a `menu_items.image` value from.
+pub(crate) fn image_url(image: &str) -> String {
+    if image.is_empty() || image.contains("://") {
+        image.to_string()
+    } else {
+        format!("{}{}", URL_PREFIX, image)
+    }
+}
+
+/// Decodes the upload and writes the original and a JPEG thumbnail, unless a
+/// file with the same content is already stored.
+fn store_files(store_dir: &Path, data: &[u8]) -> Result<(String, String, u32, u32), String> {
+    let format = image::guess_format(data).map_err(|_| "ไม่รู้จักรูปแบบไฟล์รูปภาพ".to_string())?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    ) {
+        return Err("รองรับเฉพาะไฟล์ JPEG, PNG และ WebP".to_string());
+    }
+    let decoded = image::load_from_memory_with_format(data, format)
+        .map_err(|e| format!("อ่านไฟล์รูปภาพไม่ได้: {}", e))?;
+
+    let hash = format!("{:x}", Sha256::digest(data));
+    let file_name = format!("{}.{}", hash, format.extensions_str()[0]);
+
+    std::fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    let path = store_dir.join(&file_name);
+    if !path.exists() {
+        std::fs::write(&path, data).map_err(|e| format!("บันทึกรูปภาพไม่สำเร็จ: {}", e))?;
+    }
+
+    let thumb_path = store_dir.join(thumbnail_name(&hash));
+    if !thumb_path.exists() {
+        image::DynamicImage::ImageRgb8(decoded.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8())
+            .save_with_format(&thumb_path, ImageFormat::Jpeg)
+            .map_err(|e| format!("สร้างรูปย่อไม่สำเร็จ: {}", e))?;
+    }
+
+    Ok((hash, file_name, decoded.width(), decoded.height()))
+}
+
+/// Deletes images that no available menu item uses any more. Items that were
+/// removed (made unavailable) lose their image reference, which is recorded
+/// in the menu history under `changed_by`. Images uploaded in the last few
+/// minutes are kept, as the item they belong to may not be saved yet.
+pub(crate) async fn delete_orphans(
+    conn: &mut PgConnection,
+    store: &ImageStore,
+    changed_by: &str,
+) -> Result<usize, sqlx::Error> {
+    let mut tx = conn.begin().await?;
+
+    let orphans: Vec<(String, String)> = sqlx::query_as(
+        "SELECT i.hash, i.file_name
+         FROM menu_images i
+         WHERE i.created_at < NOW() - INTERVAL '10 minutes'
+           AND NOT EXISTS (
+               SELECT 1 FROM menu_items m
+               WHERE m.image = i.file_name AND COALESCE(m.is_available, TRUE)
+           )",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (hash, file_name) in &orphans {
+        let item_ids: Vec<(i32,)> = sqlx::query_as("SELECT id FROM menu_items WHERE image = $1")
+            .bind(file_name)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (item_id,) in item_ids {
+            let Some(before) = menu_history::snapshot(&mut tx, item_id).await? else {
+                continue;
+            };
+            sqlx::query("UPDATE menu_items SET image = '' WHERE id = $1")
+                .bind(item_id)
+                .execute(&mut *tx)
+                .await?;
+            let after = MenuItemSnapshot {
+                image: String::new(),
+                ..before.clone()
+            };
+            menu_history::record_changes(&mut tx, item_id, &before, &after, changed_by).await?;
+        }
+
+        sqlx::query("DELETE FROM menu_images WHERE hash = $1")
+            .bind(hash)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    // Files go only once the rows are gone. A file that is already gone is
+    // fine; the row is what matters.
+    for (hash, file_name) in &orphans {
+        let _ = std::fs::remove_file(store.dir.join(file_name));
+        let _ = std::fs::remove_file(store.dir.join(thumbnail_name(hash)));
+    }
+
+    Ok(orphans.len())
+}
+
+/// Handler for the `menuimg` protocol. Only plain file names inside the image
+/// directory are served.
+pub fn serve_image(
+    app: &AppHandle,
+    request: &Request,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let file_name = request
+        .uri()
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default();
+
+    let valid = !file_name.is_empty()
+        && file_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && !file_name.starts_with('.');
+    if !valid {
+        return ResponseBuilder::new().status(404).body(Vec::new());
+    }
+
+    let store = app.state::<ImageStore>();
+    match std::fs::read(store.dir.join(file_name)) {
+        Ok(data) => {
+            let mimetype = match file_name.rsplit('.').next() {
+                Some("png") => "image/png",
+                Some("webp") => "image/webp",
+                _ => "image/jpeg",
+            };
+            ResponseBuilder::new()
+                .status(200)
+                .mimetype(mimetype)
+                .header("Cache-Control", "max-age=31536000, immutable")
+                .body(data)
+        }
+        Err(_) => ResponseBuilder::new().status(404).body(Vec::new()),
+    }
+}
+
+// ── Commands ──
+
+/// Stores an uploaded photo and returns the URLs to use for it. Uploading the
+/// same file twice returns the existing image.
+#[tauri::command]
+pub async fn upload_menu_image(
+    data: Vec<u8>,
+    store: State<'_, ImageStore>,
+    db: State<'_, Database>,
+) -> Result<UploadedImage, String> {
+    if data.is_empty() || data.len() > MAX_UPLOAD_BYTES {
+        return Err("ไฟล์รูปภาพต้องมีขนาดไม่เกิน 10 MB".to_string());
+    }
+
+    let dir = store.dir.clone();
+    let size = data.len() as i64;
+    let (hash, file_name, width, height) =
+        tokio::task::spawn_blocking(move || store_files(&dir, &data))
+            .await
+            .map_err(|e| e.to_string())??;
+
+    sqlx::query(
+        "INSERT INTO menu_images (hash, file_name, width, height, size_bytes)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (hash) DO UPDATE SET created_at = NOW()",
+    )
+    .bind(&hash)
+    .bind(&file_name)
+    .bind(width as i32)
+    .bind(height as i32)
+    .bind(size)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกรูปภาพไม่สำเร็จ: {}", e))?;
+
+    Ok(UploadedImage {
+        url: image_url(&file_name),
+        thumbnail_url: image_url(&thumbnail_name(&hash)),
+        image: file_name,
+        hash,
+        width,
+        height,
+    })
+}
+
+#[tauri::command]
+pub async fn delete_orphaned_images(
+    changed_by: String,
+    store: State<'_, ImageStore>,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    delete_orphans(&mut conn, &store, &changed_by)
+        .await
+        .map_err(|e| e.to_string())
+}
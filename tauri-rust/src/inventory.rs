@@ -0,0 +1,291 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::costing;
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct StockItem {
+    pub id: i32,
+    pub name: String,
+    pub unit: String,
+    pub qty: f64,
+    pub cost_per_unit: f64,
+    pub reorder_level: f64,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewStockItem {
+    pub name: String,
+    pub unit: String,
+    pub cost_per_unit: Option<f64>,
+    pub reorder_level: Option<f64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StockMovement {
+    pub id: i32,
+    pub stock_item_id: i32,
+    pub qty_change: f64,
+    pub reason: String,
+    pub reference: String,
+    pub unit_cost: f64,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Either `stock_item_id` or `menu_item_id` must be set. Wasting a menu item
+/// deducts every ingredient in its recipe.
+#[derive(Debug, Deserialize)]
+pub struct WasteInput {
+    pub stock_item_id: Option<i32>,
+    pub menu_item_id: Option<i32>,
+    pub qty: f64,
+    pub reason: String,
+    pub note: Option<String>,
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WasteEntry {
+    pub id: i32,
+    pub stock_item_id: Option<i32>,
+    pub menu_item_id: Option<i32>,
+    pub item_name: String,
+    pub qty: f64,
+    pub reason: String,
+    pub note: String,
+    pub total_cost: f64,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WasteByReason {
+    pub reason: String,
+    pub entries: i64,
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WasteReport {
+    pub start_date: String,
+    pub end_date: String,
+    pub total_cost: f64,
+    pub by_reason: Vec<WasteByReason>,
+    pub entries: Vec<WasteEntry>,
+}
+
+// ── Helpers ──
+
+/// Changes the on-hand quantity of a stock item and writes the matching
+/// movement row, so every stock change has an audit entry.
+pub(crate) async fn record_movement(
+    conn: &mut PgConnection,
+    stock_item_id: i32,
+    qty_change: f64,
+    reason: &str,
+    reference: &str,
+    unit_cost: f64,
+    created_by: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE stock_items SET qty = qty + $1 WHERE id = $2")
+        .bind(qty_change)
+        .bind(stock_item_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO stock_movements (stock_item_id, qty_change, reason, reference, unit_cost, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(stock_item_id)
+    .bind(qty_change)
+    .bind(reason)
+    .bind(reference)
+    .bind(unit_cost)
+    .bind(created_by)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_stock_items(db: State<'_, Database>) -> Result<Vec<StockItem>, String> {
+    sqlx::query_as::<_, StockItem>(
+        "SELECT id, name, unit, qty, cost_per_unit, reorder_level, is_active
+         FROM stock_items
+         WHERE is_active = TRUE
+         ORDER BY name",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_stock_item(
+    item: NewStockItem,
+    db: State<'_, Database>,
+) -> Result<StockItem, String> {
+    sqlx::query_as::<_, StockItem>(
+        "INSERT INTO stock_items (name, unit, cost_per_unit, reorder_level)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id, name, unit, qty, cost_per_unit, reorder_level, is_active",
+    )
+    .bind(&item.name)
+    .bind(&item.unit)
+    .bind(item.cost_per_unit.unwrap_or(0.0))
+    .bind(item.reorder_level.unwrap_or(0.0))
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| format!("เพิ่มวัตถุดิบไม่สำเร็จ: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_stock_movements(
+    stock_item_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<StockMovement>, String> {
+    sqlx::query_as::<_, StockMovement>(
+        "SELECT id, stock_item_id, qty_change, reason, reference, unit_cost, created_by, created_at
+         FROM stock_movements
+         WHERE stock_item_id = $1
+         ORDER BY created_at DESC, id DESC",
+    )
+    .bind(stock_item_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// ── Waste ──
+
+#[tauri::command]
+pub async fn record_waste(waste: WasteInput, db: State<'_, Database>) -> Result<i32, String> {
+    if waste.qty <= 0.0 {
+        return Err("จำนวนต้องมากกว่า 0".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    // (stock_item_id, qty to deduct, unit cost) for every affected stock item
+    let (item_name, deductions): (String, Vec<(i32, f64, f64)>) =
+        match (waste.stock_item_id, waste.menu_item_id) {
+            (Some(stock_item_id), None) => {
+                let row: Option<(String, f64)> =
+                    sqlx::query_as("SELECT name, cost_per_unit FROM stock_items WHERE id = $1")
+                        .bind(stock_item_id)
+                        .fetch_optional(&mut *tx)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                let (name, cost) = row.ok_or_else(|| "ไม่พบวัตถุดิบ".to_string())?;
+                (name, vec![(stock_item_id, waste.qty, cost)])
+            }
+            (None, Some(menu_item_id)) => {
+                let row: Option<(String,)> =
+                    sqlx::query_as("SELECT name FROM menu_items WHERE id = $1")
+                        .bind(menu_item_id)
+                        .fetch_optional(&mut *tx)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                let (name,) = row.ok_or_else(|| "ไม่พบเมนู".to_string())?;
+                let lines = costing::recipe_lines(&mut tx, menu_item_id)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let deductions = lines
+                    .into_iter()
+                    .map(|(id, qty, cost)| (id, qty * waste.qty, cost))
+                    .collect();
+                (name, deductions)
+            }
+            _ => return Err("ต้องระบุวัตถุดิบหรือเมนูอย่างใดอย่างหนึ่ง".to_string()),
+        };
+
+    let total_cost: f64 = deductions.iter().map(|(_, qty, cost)| qty * cost).sum();
+
+    let (waste_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO waste_log (stock_item_id, menu_item_id, item_name, qty, reason, note, total_cost, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         RETURNING id",
+    )
+    .bind(waste.stock_item_id)
+    .bind(waste.menu_item_id)
+    .bind(&item_name)
+    .bind(waste.qty)
+    .bind(&waste.reason)
+    .bind(waste.note.as_deref().unwrap_or_default())
+    .bind(total_cost)
+    .bind(&waste.user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("บันทึกของเสียไม่สำเร็จ: {}", e))?;
+
+    let reference = format!("waste:{}", waste_id);
+    for (stock_item_id, qty, cost) in deductions {
+        record_movement(
+            &mut tx,
+            stock_item_id,
+            -qty,
+            "waste",
+            &reference,
+            cost,
+            &waste.user_id,
+        )
+        .await
+        .map_err(|e| format!("ปรับสต็อกไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(waste_id)
+}
+
+#[tauri::command]
+pub async fn get_waste_report(
+    start_date: String,
+    end_date: String,
+    db: State<'_, Database>,
+) -> Result<WasteReport, String> {
+    let by_reason = sqlx::query_as::<_, WasteByReason>(
+        "SELECT reason, COUNT(*)::bigint AS entries, COALESCE(SUM(total_cost), 0) AS total_cost
+         FROM waste_log
+         WHERE created_at::date BETWEEN $1::date AND $2::date
+         GROUP BY reason
+         ORDER BY total_cost DESC",
+    )
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let entries = sqlx::query_as::<_, WasteEntry>(
+        "SELECT id, stock_item_id, menu_item_id, item_name, qty, reason, note, total_cost,
+                created_by, created_at
+         FROM waste_log
+         WHERE created_at::date BETWEEN $1::date AND $2::date
+         ORDER BY created_at DESC",
+    )
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(WasteReport {
+        start_date,
+        end_date,
+        total_cost: by_reason.iter().map(|r| r.total_cost).sum(),
+        by_reason,
+        entries,
+    })
+}
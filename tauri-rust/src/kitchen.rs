@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+
+/// Kitchen/bar steps in order. Lines only move forward.
+pub const KITCHEN_STATUSES: [&str; 4] = ["queued", "preparing", "ready", "served"];
+
+/// How far back the queue looks for tickets that were never marked served.
+const QUEUE_HOURS: f64 = 24.0;
+
+// ── Request / Response types ──
+
+/// An order, or a round added to a tab, waiting in the kitchen/bar queue.
+/// Its status follows its lines: `preparing` once any line is started,
+/// `ready` when every line is ready and `served` when every line is served.
+#[derive(Debug, Serialize, FromRow)]
+pub struct KitchenOrder {
+    pub order_id: Option<String>,
+    /// Set instead of `order_id` for a tab round; its lines are `tab_items`
+    /// and move with `advance_tab_item` / `advance_tab_round`.
+    pub tab_round_id: Option<i32>,
+    pub order_number: i32,
+    pub order_type: String,
+    pub table_name: Option<String>,
+    pub kitchen_status: String,
+    pub created_at: DateTime<Utc>,
+    pub age_seconds: f64,
+    #[sqlx(skip)]
+    pub items: Vec<KitchenItem>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct KitchenItem {
+    pub id: i32,
+    pub item_id: i32,
+    pub item_name: String,
+    pub qty: f64,
+    pub sold_by: String,
+    pub kitchen_status: String,
+    pub preparing_at: Option<DateTime<Utc>>,
+    pub ready_at: Option<DateTime<Utc>>,
+    pub served_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct KitchenItemRow {
+    order_id: Option<String>,
+    round_id: Option<i32>,
+    #[sqlx(flatten)]
+    item: KitchenItem,
+}
+
+/// Whose lines a kitchen step applies to.
+#[derive(Clone, Copy)]
+enum Ticket<'a> {
+    Order(&'a str),
+    TabRound(i32),
+}
+
+impl Ticket<'_> {
+    /// The line table, the ticket table and the line column pointing at it.
+    fn tables(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Ticket::Order(_) => ("order_items", "orders", "order_id"),
+            Ticket::TabRound(_) => ("tab_items", "tab_rounds", "round_id"),
+        }
+    }
+}
+
+/// Average times per menu item, in seconds. `wait` is from the order to the
+/// start of preparation, `prep` from the start (or the order, when the
+/// preparing step was skipped) to ready, and `pickup` from ready to served.
+#[derive(Debug, Serialize, FromRow)]
+pub struct PrepTimeRow {
+    pub item_id: i32,
+    pub item_name: String,
+    pub lines: i64,
+    pub avg_wait_seconds: Option<f64>,
+    pub avg_prep_seconds: Option<f64>,
+    pub avg_pickup_seconds: Option<f64>,
+}
+
+// ── Helpers ──
+
+fn status_rank(status: &str) -> Result<usize, String> {
+    KITCHEN_STATUSES
+        .iter()
+        .position(|s| *s == status)
+        .ok_or_else(|| format!("สถานะต้องเป็น {}", KITCHEN_STATUSES.join(", ")))
+}
+
+/// The step a line moves to: `status`, or the next one when not given.
+fn target_rank(current: &str, status: Option<&str>) -> Result<usize, String> {
+    let current_rank = status_rank(current)?;
+    let target_rank = match status {
+        Some(status) => status_rank(status)?,
+        None => current_rank + 1,
+    };
+    if target_rank <= current_rank || target_rank >= KITCHEN_STATUSES.len() {
+        return Err(format!("เปลี่ยนสถานะจาก {} ไม่ได้", current));
+    }
+
+    Ok(target_rank)
+}
+
+/// Moves a line of `lines_table` to `status` and stamps the time it got there.
+async fn set_item_status(
+    conn: &mut PgConnection,
+    lines_table: &str,
+    id: i32,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "UPDATE {}
+         SET kitchen_status = $1,
+             preparing_at = CASE WHEN $1 = 'preparing' THEN NOW() ELSE preparing_at END,
+             ready_at = CASE WHEN $1 = 'ready' THEN NOW() ELSE ready_at END,
+             served_at = CASE WHEN $1 = 'served' THEN NOW() ELSE served_at END
+         WHERE id = $2",
+        lines_table
+    ))
+    .bind(status)
+    .bind(id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Recomputes a ticket's status from its lines, stamping each step the first
+/// time the ticket reaches it. A ticket left without lines counts as served.
+async fn sync_status(conn: &mut PgConnection, ticket: Ticket<'_>) -> Result<String, sqlx::Error> {
+    let (lines, tickets, column) = ticket.tables();
+
+    let query = format!(
+        "SELECT CASE
+                    WHEN COALESCE(bool_and(kitchen_status = 'served'), TRUE) THEN 'served'
+                    WHEN bool_and(kitchen_status IN ('ready', 'served')) THEN 'ready'
+                    WHEN bool_or(kitchen_status <> 'queued') THEN 'preparing'
+                    ELSE 'queued'
+                END
+         FROM {}
+         WHERE {} = $1",
+        lines, column
+    );
+    let query = sqlx::query_as::<_, (String,)>(&query);
+    let (status,) = match ticket {
+        Ticket::Order(id) => query.bind(id),
+        Ticket::TabRound(id) => query.bind(id),
+    }
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let update = format!(
+        "UPDATE {}
+         SET kitchen_status = $1,
+             preparing_at = CASE WHEN $1 <> 'queued' THEN COALESCE(preparing_at, NOW())
+                                 ELSE preparing_at END,
+             ready_at = CASE WHEN $1 IN ('ready', 'served') THEN COALESCE(ready_at, NOW())
+                             ELSE ready_at END,
+             served_at = CASE WHEN $1 = 'served' THEN COALESCE(served_at, NOW())
+                              ELSE served_at END
+         WHERE id = $2",
+        tickets
+    );
+    let update = sqlx::query(&update).bind(&status);
+    match ticket {
+        Ticket::Order(id) => update.bind(id),
+        Ticket::TabRound(id) => update.bind(id),
+    }
+    .execute(conn)
+    .await?;
+
+    Ok(status)
+}
+
+/// Recomputes a tab round's status, e.g. after one of its lines was removed.
+pub(crate) async fn sync_round_status(
+    conn: &mut PgConnection,
+    round_id: i32,
+) -> Result<String, sqlx::Error> {
+    sync_status(conn, Ticket::TabRound(round_id)).await
+}
+
+/// Moves every line of a ticket that is behind `status` up to it.
+async fn advance_ticket(
+    conn: &mut PgConnection,
+    ticket: Ticket<'_>,
+    status: &str,
+) -> Result<String, String> {
+    let target_rank = status_rank(status)?;
+    let (lines_table, _, column) = ticket.tables();
+
+    let query = format!(
+        "SELECT id, kitchen_status FROM {} WHERE {} = $1 ORDER BY id FOR UPDATE",
+        lines_table, column
+    );
+    let query = sqlx::query_as::<_, (i32, String)>(&query);
+    let lines = match ticket {
+        Ticket::Order(id) => query.bind(id),
+        Ticket::TabRound(id) => query.bind(id),
+    }
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if lines.is_empty() {
+        return Err("ไม่พบออเดอร์".to_string());
+    }
+
+    for (id, current) in &lines {
+        if status_rank(current)? < target_rank {
+            set_item_status(&mut *conn, lines_table, *id, status)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    sync_status(conn, ticket).await.map_err(|e| e.to_string())
+}
+
+// ── Commands ──
+
+/// Orders and tab rounds from the last day that are not fully served, oldest
+/// first, with the lines still to be served. The window runs past midnight so
+/// late orders stay in the queue.
+#[tauri::command]
+pub async fn get_kitchen_queue(db: State<'_, Database>) -> Result<Vec<KitchenOrder>, String> {
+    let mut orders = sqlx::query_as::<_, KitchenOrder>(
+        "SELECT o.id AS order_id, NULL::integer AS tab_round_id, o.order_number, o.order_type,
+                dt.name AS table_name, o.kitchen_status, o.created_at,
+                EXTRACT(EPOCH FROM NOW() - o.created_at)::double precision AS age_seconds
+         FROM orders o
+         LEFT JOIN dining_tables dt ON dt.id = o.table_id
+         WHERE o.kitchen_status <> 'served' AND o.created_at >= NOW() - $1 * INTERVAL '1 hour'
+         UNION ALL
+         SELECT NULL::text, r.id, r.order_number, 'dine_in', dt.name, r.kitchen_status,
+                r.created_at, EXTRACT(EPOCH FROM NOW() - r.created_at)::double precision
+         FROM tab_rounds r
+         JOIN tabs t ON t.id = r.tab_id
+         JOIN dining_tables dt ON dt.id = t.table_id
+         WHERE r.kitchen_status <> 'served' AND r.created_at >= NOW() - $1 * INTERVAL '1 hour'
+         ORDER BY created_at",
+    )
+    .bind(QUEUE_HOURS)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let order_ids: Vec<&str> = orders
+        .iter()
+        .filter_map(|o| o.order_id.as_deref())
+        .collect();
+    let round_ids: Vec<i32> = orders.iter().filter_map(|o| o.tab_round_id).collect();
+    let items = sqlx::query_as::<_, KitchenItemRow>(
+        "SELECT order_id, NULL::integer AS round_id, id, item_id, item_name, qty, sold_by,
+                kitchen_status, preparing_at, ready_at, served_at
+         FROM order_items
+         WHERE order_id = ANY($1) AND kitchen_status <> 'served'
+         UNION ALL
+         SELECT NULL::text, round_id, id, item_id, item_name, qty, sold_by,
+                kitchen_status, preparing_at, ready_at, served_at
+         FROM tab_items
+         WHERE round_id = ANY($2) AND kitchen_status <> 'served'
+         ORDER BY id",
+    )
+    .bind(&order_ids)
+    .bind(&round_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut by_ticket: HashMap<(Option<String>, Option<i32>), Vec<KitchenItem>> = HashMap::new();
+    for row in items {
+        by_ticket
+            .entry((row.order_id, row.round_id))
+            .or_default()
+            .push(row.item);
+    }
+    for order in &mut orders {
+        order.items = by_ticket
+            .remove(&(order.order_id.clone(), order.tab_round_id))
+            .unwrap_or_default();
+    }
+
+    Ok(orders)
+}
+
+/// Moves one line to `status`, or to the next step when `status` is not
+/// given. Returns the order's resulting status.
+#[tauri::command]
+pub async fn advance_order_item(
+    order_item_id: i32,
+    status: Option<String>,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let line: Option<(String, String)> =
+        sqlx::query_as("SELECT order_id, kitchen_status FROM order_items WHERE id = $1 FOR UPDATE")
+            .bind(order_item_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let Some((order_id, current)) = line else {
+        return Err("ไม่พบรายการ".to_string());
+    };
+
+    let target_rank = target_rank(&current, status.as_deref())?;
+    set_item_status(
+        &mut tx,
+        "order_items",
+        order_item_id,
+        KITCHEN_STATUSES[target_rank],
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    let order_status = sync_status(&mut tx, Ticket::Order(&order_id))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(order_status)
+}
+
+/// `advance_order_item` for a line of a tab round. Returns the round's
+/// resulting status.
+#[tauri::command]
+pub async fn advance_tab_item(
+    tab_item_id: i32,
+    status: Option<String>,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let line: Option<(i32, String)> = sqlx::query_as(
+        "SELECT round_id, kitchen_status FROM tab_items
+         WHERE id = $1 AND round_id IS NOT NULL
+         FOR UPDATE",
+    )
+    .bind(tab_item_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    let Some((round_id, current)) = line else {
+        return Err("ไม่พบรายการ".to_string());
+    };
+
+    let target_rank = target_rank(&current, status.as_deref())?;
+    set_item_status(
+        &mut tx,
+        "tab_items",
+        tab_item_id,
+        KITCHEN_STATUSES[target_rank],
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    let round_status = sync_status(&mut tx, Ticket::TabRound(round_id))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(round_status)
+}
+
+/// Moves every line of an order that is behind `status` up to it, e.g. to
+/// mark a whole order ready or served at once.
+#[tauri::command]
+pub async fn advance_order(
+    order_id: String,
+    status: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let order_status = advance_ticket(&mut tx, Ticket::Order(&order_id), &status).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(order_status)
+}
+
+/// `advance_order` for a tab round.
+#[tauri::command]
+pub async fn advance_tab_round(
+    tab_round_id: i32,
+    status: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let round_status = advance_ticket(&mut tx, Ticket::TabRound(tab_round_id), &status).await?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(round_status)
+}
+
+/// Prep-time metrics per menu item over a date range, from the line
+/// timestamps of orders and tab rounds.
+#[tauri::command]
+pub async fn get_prep_time_report(
+    start_date: String,
+    end_date: String,
+    db: State<'_, Database>,
+) -> Result<Vec<PrepTimeRow>, String> {
+    sqlx::query_as::<_, PrepTimeRow>(
+        "SELECT l.item_id, MAX(l.item_name) AS item_name, COUNT(*) AS lines,
+                AVG(EXTRACT(EPOCH FROM l.preparing_at - l.ordered_at))::double precision
+                    AS avg_wait_seconds,
+                AVG(EXTRACT(EPOCH FROM l.ready_at - COALESCE(l.preparing_at, l.ordered_at)))
+                    ::double precision AS avg_prep_seconds,
+                AVG(EXTRACT(EPOCH FROM l.served_at - l.ready_at))::double precision
+                    AS avg_pickup_seconds
+         FROM (SELECT oi.item_id, oi.item_name, o.created_at AS ordered_at, oi.preparing_at,
+                      oi.ready_at, oi.served_at
+               FROM order_items oi
+               JOIN orders o ON o.id = oi.order_id
+               UNION ALL
+               SELECT ti.item_id, ti.item_name, r.created_at, ti.preparing_at, ti.ready_at,
+                      ti.served_at
+               FROM tab_items ti
+               JOIN tab_rounds r ON r.id = ti.round_id
+               WHERE ti.split_from IS NULL) l
+         WHERE l.ordered_at::date BETWEEN $1::date AND $2::date
+           AND l.ready_at IS NOT NULL
+         GROUP BY l.item_id
+         ORDER BY avg_prep_seconds DESC NULLS LAST",
+    )
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
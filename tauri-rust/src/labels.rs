@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::print_queue;
+use crate::printers::{self, Printer};
+use crate::tickets;
+
+/// Thermal label printers are 203 dpi.
+const DOTS_PER_MM: i32 = 8;
+const MARGIN: i32 = 16;
+
+// ── Request / Response types ──
+
+#[derive(FromRow)]
+struct LabelHeader {
+    order_number: i32,
+    customer_name: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct LabelLine {
+    category_id: String,
+    item_name: String,
+    qty: f64,
+    sold_by: String,
+}
+
+/// What goes on one cup sticker.
+struct Label<'a> {
+    order_number: i32,
+    customer_name: Option<&'a str>,
+    item: String,
+    time: String,
+    index: usize,
+    count: usize,
+}
+
+// ── Helpers ──
+
+/// One label per cup for items sold by the unit; a weighed line is one bag
+/// and gets a single label showing its weight.
+fn expand_labels<'a>(header: &'a LabelHeader, lines: &[&LabelLine]) -> Vec<Label<'a>> {
+    let time = header
+        .created_at
+        .with_timezone(&Local)
+        .format("%H:%M")
+        .to_string();
+
+    let items: Vec<String> = lines
+        .iter()
+        .flat_map(|line| {
+            let (copies, item) = if line.sold_by == "unit" {
+                (line.qty.round().max(1.0) as usize, line.item_name.clone())
+            } else {
+                let qty = tickets::format_qty(line.qty, &line.sold_by);
+                (1, format!("{} {}", line.item_name, qty))
+            };
+            std::iter::repeat_n(item, copies)
+        })
+        .collect();
+
+    let count = items.len();
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| Label {
+            order_number: header.order_number,
+            customer_name: header.customer_name.as_deref(),
+            item,
+            time: time.clone(),
+            index: i + 1,
+            count,
+        })
+        .collect()
+}
+
+/// The label is laid out in four rows: order number and counter, customer,
+/// item, time. Returns the row height in dots.
+fn row_height(printer: &Printer) -> i32 {
+    (printer.label_height_mm * DOTS_PER_MM - MARGIN * 2) / 4
+}
+
+fn tspl_text(out: &mut String, x: i32, y: i32, points: i32, text: &str) {
+    // Font "0" is the built-in scalable font, sized in points. Quotes inside
+    // a string are written as \["].
+    out.push_str(&format!(
+        "TEXT {},{},\"0\",0,{},{},\"{}\"\r\n",
+        x,
+        y,
+        points,
+        points,
+        text.replace('"', "\\[\"]")
+    ));
+}
+
+fn render_tspl(printer: &Printer, labels: &[Label]) -> Vec<u8> {
+    let row = row_height(printer);
+    let width = printer.label_width_mm * DOTS_PER_MM;
+    let large = row * 72 * 4 / (203 * 5);
+    let small = large * 3 / 4;
+
+    let mut out = format!(
+        "SIZE {} mm,{} mm\r\nGAP 2 mm,0 mm\r\nDIRECTION 1\r\nCODEPAGE UTF-8\r\n",
+        printer.label_width_mm, printer.label_height_mm
+    );
+    for label in labels {
+        out.push_str("CLS\r\n");
+        tspl_text(
+            &mut out,
+            MARGIN,
+            MARGIN,
+            large,
+            &format!("#{}", label.order_number),
+        );
+        tspl_text(
+            &mut out,
+            width - MARGIN - row * 2,
+            MARGIN,
+            small,
+            &format!("{}/{}", label.index, label.count),
+        );
+        if let Some(name) = label.customer_name {
+            tspl_text(&mut out, MARGIN, MARGIN + row, small, name);
+        }
+        tspl_text(&mut out, MARGIN, MARGIN + row * 2, small, &label.item);
+        tspl_text(&mut out, MARGIN, MARGIN + row * 3, small, &label.time);
+        out.push_str("PRINT 1,1\r\n");
+    }
+
+    out.into_bytes()
+}
+
+fn zpl_text(out: &mut String, x: i32, y: i32, dots: i32, text: &str) {
+    // ^ and ~ start ZPL commands, so they cannot appear in field data.
+    out.push_str(&format!(
+        "^FO{},{}^A0N,{},{}^FD{}^FS\n",
+        x,
+        y,
+        dots,
+        dots,
+        text.replace(['^', '~'], " ")
+    ));
+}
+
+fn render_zpl(printer: &Printer, labels: &[Label]) -> Vec<u8> {
+    let row = row_height(printer);
+    let width = printer.label_width_mm * DOTS_PER_MM;
+    let large = row * 4 / 5;
+    let small = large * 3 / 4;
+
+    let mut out = String::new();
+    for label in labels {
+        out.push_str(&format!(
+            "^XA\n^CI28\n^PW{}\n^LL{}\n",
+            width,
+            printer.label_height_mm * DOTS_PER_MM
+        ));
+        zpl_text(
+            &mut out,
+            MARGIN,
+            MARGIN,
+            large,
+            &format!("#{}", label.order_number),
+        );
+        zpl_text(
+            &mut out,
+            width - MARGIN - row * 2,
+            MARGIN,
+            small,
+            &format!("{}/{}", label.index, label.count),
+        );
+        if let Some(name) = label.customer_name {
+            zpl_text(&mut out, MARGIN, MARGIN + row, small, name);
+        }
+        zpl_text(&mut out, MARGIN, MARGIN + row * 2, small, &label.item);
+        zpl_text(&mut out, MARGIN, MARGIN + row * 3, small, &label.time);
+        out.push_str("^XZ\n");
+    }
+
+    out.into_bytes()
+}
+
+/// Queues cup stickers for an order on the label printers routed for each
+/// item's category; categories without a label printer get no stickers.
+/// Returns the print job ids.
+pub(crate) async fn queue_order_labels(
+    conn: &mut PgConnection,
+    order_id: &str,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let header = sqlx::query_as::<_, LabelHeader>(
+        "SELECT o.order_number, c.name AS customer_name, o.created_at
+         FROM orders o
+         LEFT JOIN customers c ON c.id = o.customer_id
+         WHERE o.id = $1",
+    )
+    .bind(order_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let lines = sqlx::query_as::<_, LabelLine>(
+        "SELECT COALESCE(m.category_id, '') AS category_id, oi.item_name, oi.qty, oi.sold_by
+         FROM order_items oi
+         LEFT JOIN menu_items m ON m.id = oi.item_id
+         WHERE oi.order_id = $1
+         ORDER BY oi.id",
+    )
+    .bind(order_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    queue_labels(conn, &header, &lines, Some(order_id)).await
+}
+
+/// Queues the cup stickers for a round added to a tab. Tabs have no
+/// customer, so the sticker carries the table name instead.
+pub(crate) async fn queue_round_labels(
+    conn: &mut PgConnection,
+    round_id: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let header = sqlx::query_as::<_, LabelHeader>(
+        "SELECT r.order_number, 'โต๊ะ ' || dt.name AS customer_name, r.created_at
+         FROM tab_rounds r
+         JOIN tabs t ON t.id = r.tab_id
+         JOIN dining_tables dt ON dt.id = t.table_id
+         WHERE r.id = $1",
+    )
+    .bind(round_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let lines = sqlx::query_as::<_, LabelLine>(
+        "SELECT COALESCE(m.category_id, '') AS category_id, ti.item_name, ti.qty, ti.sold_by
+         FROM tab_items ti
+         LEFT JOIN menu_items m ON m.id = ti.item_id
+         WHERE ti.round_id = $1
+         ORDER BY ti.id",
+    )
+    .bind(round_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    queue_labels(conn, &header, &lines, None).await
+}
+
+async fn queue_labels(
+    conn: &mut PgConnection,
+    header: &LabelHeader,
+    lines: &[LabelLine],
+    order_id: Option<&str>,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let mut job_ids = Vec::new();
+    for (printer, data) in build_labels(conn, header, lines).await? {
+        job_ids.push(print_queue::enqueue(conn, printer.id, "label", order_id, &data).await?);
+    }
+
+    Ok(job_ids)
+}
+
+async fn build_labels(
+    conn: &mut PgConnection,
+    header: &LabelHeader,
+    lines: &[LabelLine],
+) -> Result<Vec<(Printer, Vec<u8>)>, sqlx::Error> {
+    let printers: Vec<Printer> = printers::active_printers(conn)
+        .await?
+        .into_iter()
+        .filter(|p| p.role == "label")
+        .collect();
+    if printers.is_empty() {
+        return Ok(Vec::new());
+    }
+    let routes = printers::category_routes(conn).await?;
+
+    let mut by_printer: HashMap<i32, Vec<&LabelLine>> = HashMap::new();
+    for line in lines {
+        for printer_id in routes.get(&line.category_id).into_iter().flatten() {
+            by_printer.entry(*printer_id).or_default().push(line);
+        }
+    }
+
+    Ok(printers
+        .iter()
+        .filter_map(|printer| {
+            let labels = expand_labels(header, by_printer.get(&printer.id)?);
+            let data = match printer.label_language.as_str() {
+                "zpl" => render_zpl(printer, &labels),
+                _ => render_tspl(printer, &labels),
+            };
+            Some((printer.clone(), data))
+        })
+        .collect())
+}
+
+// ── Commands ──
+
+/// Queues an order's cup stickers again.
+#[tauri::command]
+pub async fn reprint_order_labels(
+    order_id: String,
+    db: State<'_, Database>,
+) -> Result<Vec<i32>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    queue_order_labels(&mut conn, &order_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Queues the cup stickers for a tab round again.
+#[tauri::command]
+pub async fn reprint_tab_round_labels(
+    round_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<i32>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    queue_round_labels(&mut conn, round_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(customer_name: Option<&str>) -> LabelHeader {
+        LabelHeader {
+            order_number: 42,
+            customer_name: customer_name.map(String::from),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn line(item_name: &str, qty: f64, sold_by: &str) -> LabelLine {
+        LabelLine {
+            category_id: "drinks".to_string(),
+            item_name: item_name.to_string(),
+            qty,
+            sold_by: sold_by.to_string(),
+        }
+    }
+
+    fn printer(label_language: &str) -> Printer {
+        Printer {
+            id: 1,
+            name: "Label".to_string(),
+            role: "label".to_string(),
+            connection: "network".to_string(),
+            host: Some("192.168.1.50".to_string()),
+            port: Some(9100),
+            queue_name: None,
+            thai_codepage: None,
+            label_language: label_language.to_string(),
+            label_width_mm: 40,
+            label_height_mm: 30,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn one_label_per_unit() {
+        let header = header(None);
+        let latte = line("Latte", 2.0, "unit");
+        let mocha = line("Mocha", 1.0, "unit");
+        let labels = expand_labels(&header, &[&latte, &mocha]);
+
+        let items: Vec<&str> = labels.iter().map(|l| l.item.as_str()).collect();
+        assert_eq!(items, ["Latte", "Latte", "Mocha"]);
+        let indexes: Vec<usize> = labels.iter().map(|l| l.index).collect();
+        assert_eq!(indexes, [1, 2, 3]);
+        assert!(labels.iter().all(|l| l.count == 3 && l.order_number == 42));
+    }
+
+    #[test]
+    fn weighed_line_gets_a_single_label() {
+        let header = header(None);
+        let beans = line("Beans", 0.25, "kg");
+        let cookies = line("Cookies", 3.5, "100g");
+        let labels = expand_labels(&header, &[&beans, &cookies]);
+
+        let items: Vec<&str> = labels.iter().map(|l| l.item.as_str()).collect();
+        assert_eq!(items, ["Beans 0.250 kg", "Cookies 350 g"]);
+        assert_eq!(labels[1].count, 2);
+    }
+
+    #[test]
+    fn tspl_escapes_quotes() {
+        let header = header(Some("Ann \"A\""));
+        let latte = line("Latte", 1.0, "unit");
+        let labels = expand_labels(&header, &[&latte]);
+        let out = String::from_utf8(render_tspl(&printer("tspl"), &labels)).unwrap();
+
+        assert!(out.contains("\"Ann \\[\"]A\\[\"]\"\r\n"));
+        assert!(out.contains("\"#42\"\r\n"));
+        assert!(out.ends_with("PRINT 1,1\r\n"));
+    }
+
+    #[test]
+    fn zpl_blanks_command_characters() {
+        let header = header(Some("A^B~C"));
+        let latte = line("Latte^FS", 1.0, "unit");
+        let labels = expand_labels(&header, &[&latte]);
+        let out = String::from_utf8(render_zpl(&printer("zpl"), &labels)).unwrap();
+
+        assert!(out.contains("^FDA B C^FS"));
+        assert!(out.contains("^FDLatte FS^FS"));
+        assert!(!out.contains('~'));
+        assert!(out.starts_with("^XA\n") && out.ends_with("^XZ\n"));
+    }
+}
@@ -1,13 +1,38 @@
 #![cfg_attr(all(not(debug_assertions), target_os = "windows"), windows_subsystem = "windows")]
 
 mod auth;
+mod barcodes;
+mod combos;
+mod costing;
+mod coupons;
+mod customers;
 mod database;
+mod dietary;
+mod gift_cards;
+mod held_orders;
+mod images;
+mod inventory;
+mod kitchen;
+mod labels;
 mod menu;
+mod menu_history;
+mod menu_io;
 mod orders;
+mod pricing;
+mod print_queue;
 mod printer;
+mod printers;
+mod purchasing;
+mod scale;
+mod stamps;
+mod stocktake;
+mod tables;
+mod tickets;
+mod translations;
 
 use database::Database;
 use dotenvy::dotenv;
+use tauri::Manager;
 
 #[tokio::main]
 async fn main() {
@@ -19,6 +44,20 @@ async fn main() {
 
     tauri::Builder::default()
         .manage(db)
+        .setup(|app| {
+            let dir = app
+                .path_resolver()
+                .app_data_dir()
+                .expect("Failed to resolve app data directory")
+                .join("images");
+            std::fs::create_dir_all(&dir)?;
+            app.manage(images::ImageStore { dir });
+
+            let pool = app.state::<Database>().pool.clone();
+            tauri::async_runtime::spawn(print_queue::run(app.handle(), pool));
+            Ok(())
+        })
+        .register_uri_scheme_protocol(images::PROTOCOL, images::serve_image)
         .invoke_handler(tauri::generate_handler![
             printer::check_printer_connection,
             printer::test_print,
@@ -33,6 +72,110 @@ async fn main() {
             orders::get_orders,
             orders::get_order_detail,
             orders::get_daily_summary,
+            inventory::get_stock_items,
+            inventory::add_stock_item,
+            inventory::get_stock_movements,
+            inventory::record_waste,
+            inventory::get_waste_report,
+            purchasing::get_suppliers,
+            purchasing::add_supplier,
+            purchasing::update_supplier,
+            purchasing::create_purchase_order,
+            purchasing::get_purchase_orders,
+            purchasing::get_purchase_order_detail,
+            purchasing::receive_purchase_order,
+            purchasing::cancel_purchase_order,
+            purchasing::get_supplier_prices,
+            costing::get_recipe,
+            costing::set_recipe,
+            costing::get_menu_item_costs,
+            costing::get_margin_report,
+            stocktake::open_stocktake,
+            stocktake::record_stocktake_count,
+            stocktake::get_stocktakes,
+            stocktake::get_stocktake_report,
+            stocktake::close_stocktake,
+            stocktake::cancel_stocktake,
+            customers::add_customer,
+            customers::update_customer,
+            customers::find_customer_by_phone,
+            customers::get_points_history,
+            customers::adjust_points,
+            customers::get_loyalty_config,
+            customers::update_loyalty_config,
+            stamps::get_stamp_config,
+            stamps::update_stamp_config,
+            stamps::get_stamp_card,
+            stamps::adjust_stamps,
+            gift_cards::sell_gift_card,
+            gift_cards::check_gift_card_balance,
+            gift_cards::get_gift_card_transactions,
+            gift_cards::deactivate_gift_card,
+            coupons::get_coupons,
+            coupons::create_coupon,
+            coupons::deactivate_coupon,
+            coupons::validate_coupon,
+            pricing::get_price_lists,
+            pricing::save_price_list,
+            pricing::delete_price_list,
+            combos::get_combos,
+            combos::save_combo,
+            combos::deactivate_combo,
+            menu::add_category,
+            menu::update_category,
+            menu::reorder_categories,
+            menu::archive_category,
+            menu::restore_category,
+            menu_io::export_menu,
+            menu_io::import_menu,
+            menu_history::get_menu_item_history,
+            menu_history::schedule_price_change,
+            menu_history::get_scheduled_price_changes,
+            menu_history::cancel_scheduled_price_change,
+            images::upload_menu_image,
+            images::delete_orphaned_images,
+            dietary::set_menu_item_dietary,
+            dietary::get_print_config,
+            dietary::update_print_config,
+            translations::get_translations,
+            translations::set_translation,
+            translations::delete_translation,
+            barcodes::find_item_by_barcode,
+            barcodes::set_menu_item_barcode,
+            scale::get_serial_ports,
+            scale::read_scale_weight,
+            scale::set_menu_item_sold_by,
+            held_orders::hold_order,
+            held_orders::list_held_orders,
+            held_orders::recall_held_order,
+            tables::get_table_layout,
+            tables::save_dining_zone,
+            tables::save_dining_table,
+            tables::open_tab,
+            tables::get_open_tabs,
+            tables::add_tab_items,
+            tables::remove_tab_item,
+            tables::transfer_tab,
+            tables::merge_tabs,
+            tables::split_tab,
+            kitchen::get_kitchen_queue,
+            kitchen::advance_order_item,
+            kitchen::advance_tab_item,
+            kitchen::advance_order,
+            kitchen::advance_tab_round,
+            kitchen::get_prep_time_report,
+            printers::get_printers,
+            printers::save_printer,
+            printers::get_category_routes,
+            printers::set_category_routes,
+            tickets::reprint_kitchen_tickets,
+            tickets::reprint_tab_round_tickets,
+            labels::reprint_order_labels,
+            labels::reprint_tab_round_labels,
+            print_queue::enqueue_print_job,
+            print_queue::get_print_jobs,
+            print_queue::retry_print_job,
+            print_queue::cancel_print_job,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
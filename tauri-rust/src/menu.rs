@@ -1,8 +1,15 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, PgConnection};
 use tauri::State;
 
 use crate::database::Database;
+use crate::dietary::{self, ALLERGENS, DIETARY_FLAGS};
+use crate::images::{self, ImageStore};
+use crate::menu_history::{self, MenuItemSnapshot};
+use crate::pricing;
+use crate::translations;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Category {
@@ -11,16 +18,42 @@ pub struct Category {
     pub icon: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct NewCategory {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct MenuItemData {
     pub id: i32,
     pub name: String,
     pub name_en: String,
+    /// Effective price right now, after any active price list.
     pub price: f64,
+    /// Price stored on the menu item, before price lists.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub base_price: f64,
     pub category_id: String,
+    /// Stored image file name, or an outside URL; show `image_url`.
     pub image: String,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub image_url: String,
     pub description: String,
     pub is_available: bool,
+    /// See `dietary::ALLERGENS` and `dietary::DIETARY_FLAGS`.
+    pub allergens: Vec<String>,
+    pub dietary_flags: Vec<String>,
+    pub calories: Option<i32>,
+    pub sku: Option<String>,
+    /// EAN-13; UPC-A codes are stored with a leading zero.
+    pub barcode: Option<String>,
+    /// `unit`, or `kg` / `100g` for items sold by weight, where `price` is
+    /// per that amount.
+    pub sold_by: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,45 +64,311 @@ pub struct NewMenuItem {
     pub category_id: String,
     pub image: Option<String>,
     pub description: Option<String>,
+    #[serde(default)]
+    pub allergens: Vec<String>,
+    #[serde(default)]
+    pub dietary_flags: Vec<String>,
+    #[serde(default)]
+    pub calories: Option<i32>,
+}
+
+pub(crate) const MENU_ITEM_COLUMNS: &str = "id, name, name_en, price, category_id, image,
+    description, is_available, allergens, dietary_flags, calories, sku, barcode, sold_by";
+
+/// Maps menu item ids to their category, for rules that apply per category.
+pub(crate) async fn item_categories(
+    conn: &mut PgConnection,
+    item_ids: &[i32],
+) -> Result<HashMap<i32, String>, sqlx::Error> {
+    let rows: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, category_id FROM menu_items WHERE id = ANY($1)")
+            .bind(item_ids)
+            .fetch_all(conn)
+            .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// Items can only be put in categories that are shown on the POS.
+async fn check_category(conn: &mut PgConnection, category_id: &str) -> Result<(), String> {
+    let found: Option<(bool,)> = sqlx::query_as("SELECT is_archived FROM categories WHERE id = $1")
+        .bind(category_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match found {
+        Some((false,)) => Ok(()),
+        Some((true,)) => Err(format!("หมวดหมู่ {} ถูกซ่อนอยู่", category_id)),
+        None => Err(format!("ไม่พบหมวดหมู่ {}", category_id)),
+    }
+}
+
+/// Cleans up images after an item stops using one. Failing to clean up must
+/// not fail the edit, so errors are ignored; `delete_orphaned_images` can be
+/// run again later.
+async fn remove_unused_images(db: &Database, store: &ImageStore, changed_by: &str) {
+    if let Ok(mut conn) = db.pool.acquire().await {
+        let _ = images::delete_orphans(&mut conn, store, changed_by).await;
+    }
+}
+
+#[tauri::command]
+pub async fn get_categories(
+    locale: Option<String>,
+    db: State<'_, Database>,
+) -> Result<Vec<Category>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let mut categories = sqlx::query_as::<_, Category>(
+        "SELECT id, name, icon FROM categories WHERE NOT is_archived ORDER BY sort_order",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if let Some(names) = translations::for_locale(&mut conn, "category", locale.as_deref()).await? {
+        for category in &mut categories {
+            if let Some(t) = names.get(&category.id) {
+                category.name = t.name.clone();
+            }
+        }
+    }
+
+    Ok(categories)
 }
 
 #[tauri::command]
-pub async fn get_categories(db: State<'_, Database>) -> Result<Vec<Category>, String> {
-    sqlx::query_as::<_, Category>("SELECT id, name, icon FROM categories ORDER BY sort_order")
-        .fetch_all(&db.pool)
+pub async fn add_category(
+    category: NewCategory,
+    db: State<'_, Database>,
+) -> Result<Category, String> {
+    let id = category.id.trim().to_lowercase();
+    if id.is_empty() {
+        return Err("กรุณาระบุรหัสหมวดหมู่".to_string());
+    }
+
+    sqlx::query_as::<_, Category>(
+        "INSERT INTO categories (id, name, icon, sort_order)
+         VALUES ($1, $2, $3, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories))
+         RETURNING id, name, icon",
+    )
+    .bind(&id)
+    .bind(&category.name)
+    .bind(&category.icon)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+        Some(c) if c == "23505" => "รหัสหมวดหมู่นี้มีอยู่แล้ว".to_string(),
+        _ => format!("เพิ่มหมวดหมู่ไม่สำเร็จ: {}", e),
+    })
+}
+
+#[tauri::command]
+pub async fn update_category(
+    id: String,
+    name: String,
+    icon: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let rows = sqlx::query("UPDATE categories SET name = $1, icon = $2 WHERE id = $3")
+        .bind(&name)
+        .bind(&icon)
+        .bind(&id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| format!("อัปเดตหมวดหมู่ไม่สำเร็จ: {}", e))?
+        .rows_affected();
+
+    Ok(rows > 0)
+}
+
+/// Sets `sort_order` to each category's position in `ids`.
+#[tauri::command]
+pub async fn reorder_categories(ids: Vec<String>, db: State<'_, Database>) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    for (sort_order, id) in ids.iter().enumerate() {
+        let rows = sqlx::query("UPDATE categories SET sort_order = $1 WHERE id = $2")
+            .bind(sort_order as i32)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("จัดลำดับหมวดหมู่ไม่สำเร็จ: {}", e))?
+            .rows_affected();
+        if rows == 0 {
+            return Err(format!("ไม่พบหมวดหมู่ {}", id));
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Hides a category. Available items must be moved out first, either
+/// beforehand or by passing `move_items_to`; moved items are recorded in the
+/// menu history.
+#[tauri::command]
+pub async fn archive_category(
+    id: String,
+    move_items_to: Option<String>,
+    changed_by: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    if let Some(target) = &move_items_to {
+        if target == &id {
+            return Err("ต้องย้ายเมนูไปหมวดหมู่อื่น".to_string());
+        }
+        let found: Option<(bool,)> =
+            sqlx::query_as("SELECT is_archived FROM categories WHERE id = $1")
+                .bind(target)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        if found != Some((false,)) {
+            return Err("ไม่พบหมวดหมู่ปลายทาง".to_string());
+        }
+
+        let item_ids: Vec<(i32,)> = sqlx::query_as(
+            "SELECT id FROM menu_items WHERE category_id = $1 AND is_available ORDER BY id",
+        )
+        .bind(&id)
+        .fetch_all(&mut *tx)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+        for (item_id,) in item_ids {
+            let Some(before) = menu_history::snapshot(&mut tx, item_id)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                continue;
+            };
+            sqlx::query("UPDATE menu_items SET category_id = $1 WHERE id = $2")
+                .bind(target)
+                .bind(item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("ย้ายเมนูไม่สำเร็จ: {}", e))?;
+            let after = MenuItemSnapshot {
+                category_id: target.clone(),
+                ..before.clone()
+            };
+            menu_history::record_changes(&mut tx, item_id, &before, &after, &changed_by)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let (active,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*)::bigint FROM menu_items WHERE category_id = $1 AND is_available",
+    )
+    .bind(&id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if active > 0 {
+        return Err(format!(
+            "ยังมีเมนูที่เปิดขายอยู่ในหมวดหมู่นี้ {} รายการ กรุณาย้ายเมนูก่อน",
+            active
+        ));
+    }
+
+    let rows = sqlx::query("UPDATE categories SET is_archived = TRUE WHERE id = $1")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("ซ่อนหมวดหมู่ไม่สำเร็จ: {}", e))?
+        .rows_affected();
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(rows > 0)
 }
 
+/// Shows an archived category again, at the end of the list.
+#[tauri::command]
+pub async fn restore_category(id: String, db: State<'_, Database>) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "UPDATE categories
+         SET is_archived = FALSE,
+             sort_order = (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories)
+         WHERE id = $1 AND is_archived",
+    )
+    .bind(&id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("แสดงหมวดหมู่ไม่สำเร็จ: {}", e))?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
+
+/// `exclude_allergens` hides items containing any of them; `dietary_flags`
+/// keeps only items that have all of them. With `locale` set, `name` and
+/// `description` are translated, falling back to Thai (or `name_en` for `en`).
 #[tauri::command]
 pub async fn get_menu_items(
     category: Option<String>,
+    locale: Option<String>,
+    exclude_allergens: Option<Vec<String>>,
+    dietary_flags: Option<Vec<String>>,
     db: State<'_, Database>,
 ) -> Result<Vec<MenuItemData>, String> {
-    match category {
-        Some(cat) => {
-            sqlx::query_as::<_, MenuItemData>(
-                "SELECT id, name, name_en, price, category_id, image, description, is_available
-                 FROM menu_items
-                 WHERE category_id = $1 AND is_available = TRUE
-                 ORDER BY id",
-            )
-            .bind(cat)
-            .fetch_all(&db.pool)
-            .await
-        }
-        None => {
-            sqlx::query_as::<_, MenuItemData>(
-                "SELECT id, name, name_en, price, category_id, image, description, is_available
-                 FROM menu_items
-                 WHERE is_available = TRUE
-                 ORDER BY category_id, id",
-            )
-            .fetch_all(&db.pool)
-            .await
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+    menu_history::apply_due_price_changes(&mut tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let mut items = sqlx::query_as::<_, MenuItemData>(&format!(
+        "SELECT {}
+         FROM menu_items
+         WHERE ($1::text IS NULL OR category_id = $1)
+           AND is_available = TRUE
+           AND NOT (allergens && $2)
+           AND dietary_flags @> $3
+         ORDER BY category_id, id",
+        MENU_ITEM_COLUMNS
+    ))
+    .bind(category)
+    .bind(exclude_allergens.unwrap_or_default())
+    .bind(dietary_flags.unwrap_or_default())
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+    let prices = pricing::active_prices(&mut conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for item in &mut items {
+        item.base_price = item.price;
+        item.price = prices.price_for(item.id, &item.category_id, item.base_price);
+        item.image_url = images::image_url(&item.image);
+    }
+
+    if let Some(names) = translations::for_locale(&mut conn, "menu_item", locale.as_deref()).await?
+    {
+        let english = locale.as_deref().map(str::trim) == Some("en");
+        for item in &mut items {
+            match names.get(&item.id.to_string()) {
+                Some(t) => {
+                    item.name = t.name.clone();
+                    if let Some(description) = &t.description {
+                        item.description = description.clone();
+                    }
+                }
+                None if english && !item.name_en.is_empty() => item.name = item.name_en.clone(),
+                None => {}
+            }
         }
     }
-    .map_err(|e| e.to_string())
+
+    Ok(items)
 }
 
 #[tauri::command]
@@ -77,23 +376,43 @@ pub async fn add_menu_item(
     item: NewMenuItem,
     db: State<'_, Database>,
 ) -> Result<MenuItemData, String> {
-    sqlx::query_as::<_, MenuItemData>(
-        "INSERT INTO menu_items (name, name_en, price, category_id, image, description)
-         VALUES ($1, $2, $3, $4, $5, $6)
-         RETURNING id, name, name_en, price, category_id, image, description, is_available",
-    )
+    let allergens = dietary::normalize_tags(&item.allergens, &ALLERGENS, "สารก่อภูมิแพ้")?;
+    let dietary_flags =
+        dietary::normalize_tags(&item.dietary_flags, &DIETARY_FLAGS, "ข้อมูลโภชนาการ")?;
+
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+    check_category(&mut conn, &item.category_id).await?;
+
+    sqlx::query_as::<_, MenuItemData>(&format!(
+        "INSERT INTO menu_items (name, name_en, price, category_id, image, description, allergens,
+                                 dietary_flags, calories)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING {}",
+        MENU_ITEM_COLUMNS
+    ))
     .bind(&item.name)
     .bind(&item.name_en)
     .bind(item.price)
     .bind(&item.category_id)
-    .bind(item.image.as_deref().unwrap_or_default())
+    .bind(images::stored_image(
+        item.image.as_deref().unwrap_or_default(),
+    ))
     .bind(item.description.as_deref().unwrap_or_default())
-    .fetch_one(&db.pool)
+    .bind(&allergens)
+    .bind(&dietary_flags)
+    .bind(item.calories)
+    .fetch_one(&mut *conn)
     .await
+    .map(|mut created| {
+        created.base_price = created.price;
+        created.image_url = images::image_url(&created.image);
+        created
+    })
     .map_err(|e| format!("เพิ่มเมนูไม่สำเร็จ: {}", e))
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_menu_item(
     id: i32,
     name: String,
@@ -102,36 +421,95 @@ pub async fn update_menu_item(
     category_id: String,
     image: Option<String>,
     description: Option<String>,
+    changed_by: String,
+    store: State<'_, ImageStore>,
     db: State<'_, Database>,
 ) -> Result<bool, String> {
-    let rows = sqlx::query(
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(before) = menu_history::snapshot(&mut tx, id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+    let after = MenuItemSnapshot {
+        name,
+        name_en,
+        price,
+        category_id,
+        image: images::stored_image(image.as_deref().unwrap_or_default()),
+        description: description.unwrap_or_default(),
+        ..before.clone()
+    };
+    if after.category_id != before.category_id {
+        check_category(&mut tx, &after.category_id).await?;
+    }
+
+    sqlx::query(
         "UPDATE menu_items
          SET name = $1, name_en = $2, price = $3, category_id = $4, image = $5, description = $6
          WHERE id = $7",
     )
-    .bind(&name)
-    .bind(&name_en)
-    .bind(price)
-    .bind(&category_id)
-    .bind(image.as_deref().unwrap_or_default())
-    .bind(description.as_deref().unwrap_or_default())
+    .bind(&after.name)
+    .bind(&after.name_en)
+    .bind(after.price)
+    .bind(&after.category_id)
+    .bind(&after.image)
+    .bind(&after.description)
     .bind(id)
-    .execute(&db.pool)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| format!("อัปเดตเมนูไม่สำเร็จ: {}", e))?
-    .rows_affected();
+    .map_err(|e| format!("อัปเดตเมนูไม่สำเร็จ: {}", e))?;
 
-    Ok(rows > 0)
+    menu_history::record_changes(&mut tx, id, &before, &after, &changed_by)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    if before.image != after.image {
+        remove_unused_images(&db, &store, &changed_by).await;
+    }
+
+    Ok(true)
 }
 
 #[tauri::command]
-pub async fn delete_menu_item(id: i32, db: State<'_, Database>) -> Result<bool, String> {
-    let rows = sqlx::query("UPDATE menu_items SET is_available = FALSE WHERE id = $1")
+pub async fn delete_menu_item(
+    id: i32,
+    changed_by: String,
+    store: State<'_, ImageStore>,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(before) = menu_history::snapshot(&mut tx, id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query("UPDATE menu_items SET is_available = FALSE WHERE id = $1")
         .bind(id)
-        .execute(&db.pool)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| format!("ลบเมนูไม่สำเร็จ: {}", e))?
-        .rows_affected();
+        .map_err(|e| format!("ลบเมนูไม่สำเร็จ: {}", e))?;
 
-    Ok(rows > 0)
+    let after = MenuItemSnapshot {
+        is_available: false,
+        ..before.clone()
+    };
+    menu_history::record_changes(&mut tx, id, &before, &after, &changed_by)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    if !before.image.is_empty() {
+        remove_unused_images(&db, &store, &changed_by).await;
+    }
+
+    Ok(true)
 }
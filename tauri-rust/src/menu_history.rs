@@ -0,0 +1,309 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct MenuItemChange {
+    pub id: i32,
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ScheduledPriceChange {
+    pub id: i32,
+    pub menu_item_id: i32,
+    pub item_name: String,
+    pub current_price: f64,
+    pub new_price: f64,
+    pub effective_date: NaiveDate,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub is_cancelled: bool,
+}
+
+/// The editable fields of a menu item, compared before and after a change.
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct MenuItemSnapshot {
+    pub name: String,
+    pub name_en: String,
+    pub price: f64,
+    pub category_id: String,
+    pub image: String,
+    pub description: String,
+    pub is_available: bool,
+    pub allergens: Vec<String>,
+    pub dietary_flags: Vec<String>,
+    pub calories: Option<i32>,
+    pub sku: Option<String>,
+    pub barcode: Option<String>,
+    pub sold_by: String,
+}
+
+// ── Helpers ──
+
+/// Reads and locks a menu item so the caller can update it and record the
+/// difference with `record_changes`.
+pub(crate) async fn snapshot(
+    conn: &mut PgConnection,
+    menu_item_id: i32,
+) -> Result<Option<MenuItemSnapshot>, sqlx::Error> {
+    sqlx::query_as::<_, MenuItemSnapshot>(
+        "SELECT name, name_en, price, category_id, COALESCE(image, '') AS image,
+                COALESCE(description, '') AS description,
+                COALESCE(is_available, TRUE) AS is_available, allergens, dietary_flags, calories, sku, barcode,
+                sold_by
+         FROM menu_items
+         WHERE id = $1
+         FOR UPDATE",
+    )
+    .bind(menu_item_id)
+    .fetch_optional(conn)
+    .await
+}
+
+/// Writes one history row per field that differs between `before` and `after`.
+pub(crate) async fn record_changes(
+    conn: &mut PgConnection,
+    menu_item_id: i32,
+    before: &MenuItemSnapshot,
+    after: &MenuItemSnapshot,
+    changed_by: &str,
+) -> Result<(), sqlx::Error> {
+    let fields = [
+        ("name", before.name.clone(), after.name.clone()),
+        ("name_en", before.name_en.clone(), after.name_en.clone()),
+        ("price", before.price.to_string(), after.price.to_string()),
+        (
+            "category_id",
+            before.category_id.clone(),
+            after.category_id.clone(),
+        ),
+        ("image", before.image.clone(), after.image.clone()),
+        (
+            "description",
+            before.description.clone(),
+            after.description.clone(),
+        ),
+        (
+            "is_available",
+            before.is_available.to_string(),
+            after.is_available.to_string(),
+        ),
+        (
+            "allergens",
+            before.allergens.join(","),
+            after.allergens.join(","),
+        ),
+        (
+            "dietary_flags",
+            before.dietary_flags.join(","),
+            after.dietary_flags.join(","),
+        ),
+        (
+            "calories",
+            before.calories.map_or(String::new(), |c| c.to_string()),
+            after.calories.map_or(String::new(), |c| c.to_string()),
+        ),
+        (
+            "sku",
+            before.sku.clone().unwrap_or_default(),
+            after.sku.clone().unwrap_or_default(),
+        ),
+        (
+            "barcode",
+            before.barcode.clone().unwrap_or_default(),
+            after.barcode.clone().unwrap_or_default(),
+        ),
+        ("sold_by", before.sold_by.clone(), after.sold_by.clone()),
+    ];
+
+    for (field, old_value, new_value) in fields {
+        if old_value != new_value {
+            insert_change(
+                &mut *conn,
+                menu_item_id,
+                field,
+                &old_value,
+                &new_value,
+                changed_by,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn insert_change(
+    conn: &mut PgConnection,
+    menu_item_id: i32,
+    field: &str,
+    old_value: &str,
+    new_value: &str,
+    changed_by: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO menu_item_history (menu_item_id, field, old_value, new_value, changed_by)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(menu_item_id)
+    .bind(field)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(changed_by)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Applies scheduled price changes whose date has arrived. Called before
+/// prices are read, so no background job is needed.
+pub(crate) async fn apply_due_price_changes(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let due: Vec<(i32, i32, f64, String)> = sqlx::query_as(
+        "SELECT id, menu_item_id, new_price, created_by
+         FROM scheduled_price_changes
+         WHERE applied_at IS NULL AND NOT is_cancelled AND effective_date <= $1
+         ORDER BY effective_date, id
+         FOR UPDATE SKIP LOCKED",
+    )
+    .bind(Local::now().date_naive())
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (id, menu_item_id, new_price, created_by) in due {
+        let old_price: Option<(f64,)> =
+            sqlx::query_as("SELECT price FROM menu_items WHERE id = $1 FOR UPDATE")
+                .bind(menu_item_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+        if let Some((old_price,)) = old_price {
+            sqlx::query("UPDATE menu_items SET price = $1 WHERE id = $2")
+                .bind(new_price)
+                .bind(menu_item_id)
+                .execute(&mut *conn)
+                .await?;
+
+            if old_price != new_price {
+                insert_change(
+                    &mut *conn,
+                    menu_item_id,
+                    "price",
+                    &old_price.to_string(),
+                    &new_price.to_string(),
+                    &created_by,
+                )
+                .await?;
+            }
+        }
+
+        sqlx::query("UPDATE scheduled_price_changes SET applied_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_menu_item_history(
+    menu_item_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<MenuItemChange>, String> {
+    sqlx::query_as::<_, MenuItemChange>(
+        "SELECT id, field, old_value, new_value, changed_by, changed_at
+         FROM menu_item_history
+         WHERE menu_item_id = $1
+         ORDER BY changed_at DESC, id DESC",
+    )
+    .bind(menu_item_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn schedule_price_change(
+    menu_item_id: i32,
+    new_price: f64,
+    effective_date: String,
+    created_by: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    if new_price < 0.0 {
+        return Err("ราคาต้องไม่ติดลบ".to_string());
+    }
+    let effective_date = NaiveDate::parse_from_str(&effective_date, "%Y-%m-%d")
+        .map_err(|_| "รูปแบบวันที่ต้องเป็น YYYY-MM-DD".to_string())?;
+    if effective_date <= Local::now().date_naive() {
+        return Err("วันที่มีผลต้องเป็นวันในอนาคต".to_string());
+    }
+
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO scheduled_price_changes (menu_item_id, new_price, effective_date, created_by)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id",
+    )
+    .bind(menu_item_id)
+    .bind(new_price)
+    .bind(effective_date)
+    .bind(&created_by)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| format!("ตั้งเวลาเปลี่ยนราคาไม่สำเร็จ: {}", e))?;
+
+    Ok(id)
+}
+
+/// Pending changes, plus the applied and cancelled history when
+/// `include_done` is set.
+#[tauri::command]
+pub async fn get_scheduled_price_changes(
+    include_done: Option<bool>,
+    db: State<'_, Database>,
+) -> Result<Vec<ScheduledPriceChange>, String> {
+    sqlx::query_as::<_, ScheduledPriceChange>(
+        "SELECT s.id, s.menu_item_id, m.name AS item_name, m.price AS current_price, s.new_price,
+                s.effective_date, s.created_by, s.created_at, s.applied_at, s.is_cancelled
+         FROM scheduled_price_changes s
+         JOIN menu_items m ON m.id = s.menu_item_id
+         WHERE $1 OR (s.applied_at IS NULL AND NOT s.is_cancelled)
+         ORDER BY s.effective_date, s.id",
+    )
+    .bind(include_done.unwrap_or(false))
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_scheduled_price_change(
+    id: i32,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "UPDATE scheduled_price_changes SET is_cancelled = TRUE
+         WHERE id = $1 AND applied_at IS NULL",
+    )
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
@@ -0,0 +1,511 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::barcodes;
+use crate::database::Database;
+use crate::dietary::{self, ALLERGENS, DIETARY_FLAGS};
+use crate::images;
+use crate::menu_history::{self, MenuItemSnapshot};
+use crate::scale::SOLD_BY;
+
+// ── Request / Response types ──
+
+/// JSON export. CSV carries the same data as one row per item, with the
+/// category's name and icon repeated on each row.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuExport {
+    pub categories: Vec<CategoryRow>,
+    pub items: Vec<ItemRow>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryRow {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+    #[serde(default)]
+    pub sort_order: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ItemRow {
+    #[serde(default)]
+    pub sku: Option<String>,
+    #[serde(default)]
+    pub barcode: Option<String>,
+    pub name: String,
+    pub name_en: String,
+    pub price: f64,
+    /// `unit`, `kg` or `100g`; `price` is per that unit.
+    #[serde(default = "default_sold_by")]
+    pub sold_by: String,
+    pub category_id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default = "default_available")]
+    pub is_available: bool,
+    #[serde(default)]
+    pub allergens: Vec<String>,
+    #[serde(default)]
+    pub dietary_flags: Vec<String>,
+    #[serde(default)]
+    pub calories: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CsvRow {
+    sku: Option<String>,
+    #[serde(default)]
+    barcode: Option<String>,
+    name: String,
+    name_en: String,
+    price: f64,
+    #[serde(default = "default_sold_by")]
+    sold_by: String,
+    category_id: String,
+    category_name: String,
+    category_icon: String,
+    description: String,
+    image: String,
+    is_available: bool,
+    /// Tags separated by `;`.
+    allergens: String,
+    dietary_flags: String,
+    calories: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportConflict {
+    /// 1-based position of the item in the file.
+    pub row: usize,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub categories_created: Vec<String>,
+    pub categories_updated: Vec<String>,
+    pub items_created: Vec<String>,
+    pub items_updated: Vec<String>,
+    pub conflicts: Vec<ImportConflict>,
+    /// False for a dry run, or when conflicts stopped the import.
+    pub applied: bool,
+}
+
+// ── Helpers ──
+
+fn default_available() -> bool {
+    true
+}
+
+fn default_sold_by() -> String {
+    "unit".to_string()
+}
+
+fn parse(format: &str, content: &str) -> Result<MenuExport, String> {
+    match format {
+        "json" => serde_json::from_str(content).map_err(|e| format!("อ่านไฟล์ JSON ไม่ได้: {}", e)),
+        "csv" => {
+            let mut categories: Vec<CategoryRow> = Vec::new();
+            let mut items = Vec::new();
+            for (i, row) in csv::Reader::from_reader(content.as_bytes())
+                .deserialize::<CsvRow>()
+                .enumerate()
+            {
+                let row = row.map_err(|e| format!("อ่านไฟล์ CSV แถวที่ {} ไม่ได้: {}", i + 2, e))?;
+                if !categories.iter().any(|c| c.id == row.category_id) {
+                    categories.push(CategoryRow {
+                        id: row.category_id.clone(),
+                        name: row.category_name,
+                        icon: row.category_icon,
+                        sort_order: categories.len() as i32 + 1,
+                    });
+                }
+                items.push(ItemRow {
+                    sku: row.sku,
+                    barcode: row.barcode,
+                    name: row.name,
+                    name_en: row.name_en,
+                    price: row.price,
+                    sold_by: row.sold_by,
+                    category_id: row.category_id,
+                    description: row.description,
+                    image: row.image,
+                    is_available: row.is_available,
+                    allergens: split_tags(&row.allergens),
+                    dietary_flags: split_tags(&row.dietary_flags),
+                    calories: row.calories,
+                });
+            }
+            Ok(MenuExport { categories, items })
+        }
+        _ => Err("รูปแบบไฟล์ต้องเป็น csv หรือ json".to_string()),
+    }
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(';')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn item_key<'a>(item: &'a ItemRow, key: &str) -> Option<&'a str> {
+    match key {
+        "sku" => item.sku.as_deref(),
+        _ => Some(item.name_en.as_str()),
+    }
+    .map(str::trim)
+    .filter(|k| !k.is_empty())
+}
+
+async fn load_menu(conn: &mut PgConnection) -> Result<MenuExport, sqlx::Error> {
+    let categories = sqlx::query_as::<_, CategoryRow>(
+        "SELECT id, name, icon, COALESCE(sort_order, 0) AS sort_order
+         FROM categories
+         WHERE NOT is_archived
+         ORDER BY sort_order, id",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let items = sqlx::query_as::<_, ItemRow>(
+        "SELECT m.sku, m.barcode, m.name, m.name_en, m.price, m.sold_by, m.category_id,
+                COALESCE(m.description, '') AS description, COALESCE(m.image, '') AS image,
+                COALESCE(m.is_available, TRUE) AS is_available, m.allergens, m.dietary_flags,
+                m.calories
+         FROM menu_items m
+         JOIN categories c ON c.id = m.category_id
+         WHERE NOT c.is_archived
+         ORDER BY c.sort_order, m.id",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(MenuExport { categories, items })
+}
+
+// ── Commands ──
+
+/// Returns the menu as CSV or JSON text; the frontend saves it to a file.
+/// Modifiers are not part of the export because menu items have none.
+#[tauri::command]
+pub async fn export_menu(format: String, db: State<'_, Database>) -> Result<String, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+    let menu = load_menu(&mut conn).await.map_err(|e| e.to_string())?;
+
+    match format.as_str() {
+        "json" => serde_json::to_string_pretty(&menu).map_err(|e| e.to_string()),
+        "csv" => {
+            let categories: HashMap<&str, &CategoryRow> =
+                menu.categories.iter().map(|c| (c.id.as_str(), c)).collect();
+
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for item in &menu.items {
+                let category = categories[item.category_id.as_str()];
+                writer
+                    .serialize(CsvRow {
+                        sku: item.sku.clone(),
+                        barcode: item.barcode.clone(),
+                        name: item.name.clone(),
+                        name_en: item.name_en.clone(),
+                        price: item.price,
+                        sold_by: item.sold_by.clone(),
+                        category_id: item.category_id.clone(),
+                        category_name: category.name.clone(),
+                        category_icon: category.icon.clone(),
+                        description: item.description.clone(),
+                        image: item.image.clone(),
+                        is_available: item.is_available,
+                        allergens: item.allergens.join(";"),
+                        dietary_flags: item.dietary_flags.join(";"),
+                        calories: item.calories,
+                    })
+                    .map_err(|e| e.to_string())?;
+            }
+            let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        }
+        _ => Err("รูปแบบไฟล์ต้องเป็น csv หรือ json".to_string()),
+    }
+}
+
+/// Imports a menu exported by `export_menu`. Items are matched to existing
+/// ones by `key` (`name_en` or `sku`). With `dry_run` set, or when any row
+/// conflicts, nothing is written and the report shows what would happen.
+#[tauri::command]
+pub async fn import_menu(
+    format: String,
+    content: String,
+    key: String,
+    dry_run: bool,
+    imported_by: String,
+    db: State<'_, Database>,
+) -> Result<ImportReport, String> {
+    if key != "name_en" && key != "sku" {
+        return Err("คีย์สำหรับจับคู่ต้องเป็น name_en หรือ sku".to_string());
+    }
+    let mut import = parse(&format, &content)?;
+    for item in &mut import.items {
+        item.image = images::stored_image(&item.image);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+    let mut report = ImportReport::default();
+
+    let existing_categories: HashMap<String, (String, String)> =
+        sqlx::query_as::<_, (String, String, String)>(
+            "SELECT id, name, icon FROM categories WHERE NOT is_archived",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(id, name, icon)| (id, (name, icon)))
+        .collect();
+
+    // Archived categories stay archived; restore them with `restore_category`
+    // before importing into them.
+    let archived_categories: HashSet<String> =
+        sqlx::query_as::<_, (String,)>("SELECT id FROM categories WHERE is_archived")
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(id,)| id)
+            .collect();
+    let import_categories: Vec<&CategoryRow> = import
+        .categories
+        .iter()
+        .filter(|c| !archived_categories.contains(&c.id))
+        .collect();
+
+    let mut existing_items: HashMap<String, Vec<i32>> = HashMap::new();
+    let rows: Vec<(i32, Option<String>)> = sqlx::query_as(&format!(
+        "SELECT id, {} FROM menu_items",
+        if key == "sku" { "sku" } else { "name_en" }
+    ))
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    for (id, k) in rows {
+        if let Some(k) = k {
+            existing_items
+                .entry(k.trim().to_string())
+                .or_default()
+                .push(id);
+        }
+    }
+
+    let known_categories: HashSet<&str> = existing_categories
+        .keys()
+        .map(String::as_str)
+        .chain(import_categories.iter().map(|c| c.id.as_str()))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut seen_barcodes = HashSet::new();
+    let mut planned = Vec::new();
+    for (i, item) in import.items.iter().enumerate() {
+        let conflict = |reason: &str| ImportConflict {
+            row: i + 1,
+            name: item.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        let Some(k) = item_key(item, &key) else {
+            report.conflicts.push(conflict("ไม่มีค่าคีย์สำหรับจับคู่"));
+            continue;
+        };
+        if !seen.insert(k) {
+            report.conflicts.push(conflict("คีย์ซ้ำกับแถวอื่นในไฟล์"));
+            continue;
+        }
+        if item.price < 0.0 {
+            report.conflicts.push(conflict("ราคาติดลบ"));
+            continue;
+        }
+        if archived_categories.contains(&item.category_id) {
+            report
+                .conflicts
+                .push(conflict(&format!("หมวดหมู่ {} ถูกซ่อนอยู่", item.category_id)));
+            continue;
+        }
+        if !known_categories.contains(item.category_id.as_str()) {
+            report.conflicts.push(conflict("ไม่พบหมวดหมู่"));
+            continue;
+        }
+        if !SOLD_BY.contains(&item.sold_by.as_str()) {
+            report
+                .conflicts
+                .push(conflict(&format!("หน่วยขายต้องเป็น {}", SOLD_BY.join(", "))));
+            continue;
+        }
+        let barcode = match item
+            .barcode
+            .as_deref()
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(barcodes::normalize_barcode)
+            .transpose()
+        {
+            Ok(barcode) => barcode,
+            Err(e) => {
+                report.conflicts.push(conflict(&e));
+                continue;
+            }
+        };
+        if let Some(code) = &barcode {
+            if !seen_barcodes.insert(code.clone()) {
+                report.conflicts.push(conflict("บาร์โค้ดซ้ำกับแถวอื่นในไฟล์"));
+                continue;
+            }
+        }
+        let tags = dietary::normalize_tags(&item.allergens, &ALLERGENS, "สารก่อภูมิแพ้").and_then(
+            |allergens| {
+                dietary::normalize_tags(&item.dietary_flags, &DIETARY_FLAGS, "ข้อมูลโภชนาการ")
+                    .map(|flags| (allergens, flags))
+            },
+        );
+        let (allergens, dietary_flags) = match tags {
+            Ok(tags) => tags,
+            Err(e) => {
+                report.conflicts.push(conflict(&e));
+                continue;
+            }
+        };
+
+        match existing_items.get(k).map(Vec::as_slice) {
+            None => {
+                report.items_created.push(item.name.clone());
+                planned.push((None, item, barcode, allergens, dietary_flags));
+            }
+            Some([id]) => {
+                report.items_updated.push(item.name.clone());
+                planned.push((Some(*id), item, barcode, allergens, dietary_flags));
+            }
+            Some(_) => report
+                .conflicts
+                .push(conflict("พบเมนูเดิมที่ใช้คีย์นี้มากกว่า 1 รายการ")),
+        }
+    }
+
+    for category in &import_categories {
+        match existing_categories.get(&category.id) {
+            None => report.categories_created.push(category.id.clone()),
+            Some((name, icon)) if name != &category.name || icon != &category.icon => {
+                report.categories_updated.push(category.id.clone())
+            }
+            Some(_) => {}
+        }
+    }
+
+    if dry_run || !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    for category in &import_categories {
+        sqlx::query(
+            "INSERT INTO categories (id, name, icon, sort_order)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, icon = EXCLUDED.icon",
+        )
+        .bind(&category.id)
+        .bind(&category.name)
+        .bind(&category.icon)
+        .bind(category.sort_order)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("นำเข้าหมวดหมู่ {} ไม่สำเร็จ: {}", category.id, e))?;
+    }
+
+    for (id, item, barcode, allergens, dietary_flags) in planned {
+        let before = match id {
+            Some(id) => menu_history::snapshot(&mut tx, id)
+                .await
+                .map_err(|e| e.to_string())?,
+            None => None,
+        };
+
+        let query = match id {
+            Some(_) => {
+                "UPDATE menu_items
+                 SET sku = $1, name = $2, name_en = $3, price = $4, category_id = $5,
+                     description = $6, image = $7, is_available = $8, allergens = $9,
+                     dietary_flags = $10, calories = $11, barcode = $12, sold_by = $13
+                 WHERE id = $14"
+            }
+            None => {
+                "INSERT INTO menu_items (sku, name, name_en, price, category_id, description, image,
+                                         is_available, allergens, dietary_flags, calories,
+                                         barcode, sold_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+            }
+        };
+
+        let mut query = sqlx::query(query)
+            .bind(item.sku.as_deref().map(str::trim).filter(|s| !s.is_empty()))
+            .bind(&item.name)
+            .bind(&item.name_en)
+            .bind(item.price)
+            .bind(&item.category_id)
+            .bind(&item.description)
+            .bind(&item.image)
+            .bind(item.is_available)
+            .bind(&allergens)
+            .bind(&dietary_flags)
+            .bind(item.calories)
+            .bind(&barcode)
+            .bind(&item.sold_by);
+        if let Some(id) = id {
+            query = query.bind(id);
+        }
+
+        query.execute(&mut *tx).await.map_err(|e| {
+            match e.as_database_error().and_then(|d| d.code()) {
+                Some(c) if c == "23505" => {
+                    format!("บาร์โค้ดหรือ SKU ของเมนู {} ใช้กับเมนูอื่นแล้ว", item.name)
+                }
+                _ => format!("นำเข้าเมนู {} ไม่สำเร็จ: {}", item.name, e),
+            }
+        })?;
+
+        if let (Some(id), Some(before)) = (id, before) {
+            let after = MenuItemSnapshot {
+                name: item.name.clone(),
+                name_en: item.name_en.clone(),
+                price: item.price,
+                category_id: item.category_id.clone(),
+                image: item.image.clone(),
+                description: item.description.clone(),
+                is_available: item.is_available,
+                allergens,
+                dietary_flags,
+                calories: item.calories,
+                sku: item
+                    .sku
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from),
+                barcode,
+                sold_by: item.sold_by.clone(),
+            };
+            menu_history::record_changes(&mut tx, id, &before, &after, &imported_by)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    report.applied = true;
+
+    Ok(report)
+}
@@ -1,10 +1,26 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use tauri::State;
 use uuid::Uuid;
 
+use crate::combos::{self, ComboOrderInput};
+use crate::costing;
+use crate::coupons;
+use crate::customers;
 use crate::database::Database;
+use crate::dietary;
+use crate::gift_cards::{self, GiftCardTender};
+use crate::inventory;
+use crate::labels;
+use crate::menu_history;
+use crate::pricing;
+use crate::stamps;
+use crate::tables;
+use crate::tickets;
+use crate::translations;
 
 // ── Request / Response types ──
 
@@ -12,8 +28,28 @@ use crate::database::Database;
 pub struct OrderItemInput {
     pub item_id: i32,
     pub item_name: String,
-    pub qty: i32,
+    /// Pieces, or the weight in kg / 100 g for items sold by weight.
+    pub qty: f64,
     pub price: f64,
+    /// Free item redeemed from the customer's stamp card; saved at price 0.
+    #[serde(default)]
+    pub is_reward: bool,
+    /// Set on component lines expanded from a combo; `combo_index` tells
+    /// apart two of the same combo on one order.
+    #[serde(skip)]
+    pub combo_id: Option<i32>,
+    #[serde(skip)]
+    pub combo_index: Option<i32>,
+    /// Copied from the menu item by `pricing::check_order_prices`; combo
+    /// components are always sold by the unit.
+    #[serde(skip)]
+    pub sold_by: String,
+}
+
+impl OrderItemInput {
+    pub(crate) fn is_weighed(&self) -> bool {
+        matches!(self.sold_by.as_str(), "kg" | "100g")
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -21,7 +57,26 @@ pub struct CreateOrderResult {
     pub success: bool,
     pub order_id: Option<String>,
     pub order_number: Option<i32>,
+    pub points_earned: Option<i32>,
+    pub stamps_earned: Option<i32>,
     pub error: Option<String>,
+    /// Kitchen/bar tickets and cup stickers queued for this order; none when
+    /// settling a tab. Their progress arrives as `print-job` events.
+    pub print_jobs: Vec<i32>,
+}
+
+impl CreateOrderResult {
+    fn failed(error: String) -> Self {
+        CreateOrderResult {
+            success: false,
+            order_id: None,
+            order_number: None,
+            points_earned: None,
+            stamps_earned: None,
+            error: Some(error),
+            print_jobs: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -33,6 +88,9 @@ pub struct OrderSummary {
     pub cashier_name: String,
     pub created_at: DateTime<Utc>,
     pub item_count: i64,
+    /// `dine_in`, `takeaway` or `delivery`; see `tables::ORDER_TYPES`.
+    pub order_type: String,
+    pub table_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -44,6 +102,9 @@ pub struct OrderDetail {
     pub cashier_id: String,
     pub cashier_name: String,
     pub created_at: DateTime<Utc>,
+    pub order_type: String,
+    pub table_id: Option<i32>,
+    pub note: Option<String>,
     #[sqlx(skip)]
     pub items: Vec<OrderItemDetail>,
 }
@@ -52,9 +113,17 @@ pub struct OrderDetail {
 pub struct OrderItemDetail {
     pub item_id: i32,
     pub item_name: String,
-    pub qty: i32,
+    pub qty: f64,
+    /// `unit`, `kg` or `100g`; see `scale::SOLD_BY`.
+    pub sold_by: String,
     pub price: f64,
     pub subtotal: f64,
+    pub combo_id: Option<i32>,
+    pub combo_index: Option<i32>,
+    /// Allergen and dietary line for the receipt; only filled when
+    /// `print_config.show_dietary_info` is on.
+    #[sqlx(skip)]
+    pub dietary_note: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -65,19 +134,27 @@ pub struct DailySummary {
     pub cash_total: f64,
     pub promptpay_total: f64,
     pub card_total: f64,
+    pub gift_card_redeemed: f64,
+    pub gift_card_sales: f64,
 }
 
 // ── Helpers ──
 
-async fn next_order_number(pool: &sqlx::PgPool) -> i32 {
+/// Today's next number. Tab rounds sent to the kitchen share the sequence, so
+/// a number called out at the counter is never on two tickets.
+pub(crate) async fn next_order_number(pool: &sqlx::PgPool) -> i32 {
     let today = Local::now().date_naive();
 
-    let row: (Option<i32>,) =
-        sqlx::query_as("SELECT MAX(order_number) FROM orders WHERE created_at::date = $1")
-            .bind(today)
-            .fetch_one(pool)
-            .await
-            .unwrap_or((None,));
+    let row: (Option<i32>,) = sqlx::query_as(
+        "SELECT MAX(order_number)
+         FROM (SELECT order_number FROM orders WHERE created_at::date = $1
+               UNION ALL
+               SELECT order_number FROM tab_rounds WHERE created_at::date = $1) numbers",
+    )
+    .bind(today)
+    .fetch_one(pool)
+    .await
+    .unwrap_or((None,));
 
     row.0.unwrap_or(0) + 1
 }
@@ -85,22 +162,146 @@ async fn next_order_number(pool: &sqlx::PgPool) -> i32 {
 // ── Commands ──
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_order(
-    items: Vec<OrderItemInput>,
+    mut items: Vec<OrderItemInput>,
     total: f64,
     payment_method: String,
     cashier_id: String,
     cashier_name: String,
+    customer_id: Option<i32>,
+    redeem_points: Option<i32>,
+    coupon_code: Option<String>,
+    gift_card: Option<GiftCardTender>,
+    combos: Option<Vec<ComboOrderInput>>,
+    order_type: Option<String>,
+    tab_id: Option<i32>,
+    split_ways: Option<i32>,
+    note: Option<String>,
     db: State<'_, Database>,
 ) -> Result<CreateOrderResult, String> {
     let order_id = Uuid::new_v4().to_string();
     let order_number = next_order_number(&db.pool).await;
 
+    let order_type = match (tab_id, order_type.as_deref()) {
+        (Some(_), _) => "dine_in",
+        (None, None) => "takeaway",
+        (None, Some(t)) => match tables::ORDER_TYPES.iter().find(|&&known| known == t) {
+            Some(known) => known,
+            None => {
+                return Ok(CreateOrderResult::failed(format!(
+                    "ประเภทออเดอร์ต้องเป็น {}",
+                    tables::ORDER_TYPES.join(", ")
+                )))
+            }
+        },
+    };
+
     let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
 
+    menu_history::apply_due_price_changes(&mut tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // A tab is charged from its own lines, at the prices they were added at.
+    let mut table_id = None;
+    let mut tab_amount = None;
+    let mut tab_ways = 1;
+    if let Some(tab_id) = tab_id {
+        if !items.is_empty() || combos.as_ref().is_some_and(|c| !c.is_empty()) {
+            return Ok(CreateOrderResult::failed(
+                "เพิ่มรายการในบิลโต๊ะก่อนชำระเงิน".to_string(),
+            ));
+        }
+        match tables::charge_tab(&mut tx, tab_id, split_ways).await {
+            Ok(charge) => {
+                table_id = Some(charge.table_id);
+                tab_amount = Some(charge.amount);
+                tab_ways = charge.ways;
+                items = charge.items;
+            }
+            Err(e) => return Ok(CreateOrderResult::failed(e)),
+        }
+    } else {
+        if let Err(e) = pricing::check_order_prices(&mut tx, &mut items).await {
+            return Ok(CreateOrderResult::failed(e));
+        }
+
+        match combos::expand(&mut tx, combos.as_deref().unwrap_or_default()).await {
+            Ok(lines) => items.extend(lines),
+            Err(e) => return Ok(CreateOrderResult::failed(e)),
+        }
+    }
+
+    let loyalty = match customer_id {
+        Some(_) => Some(
+            customers::loyalty_config(&mut tx)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+
+    let mut discount = 0.0;
+    let redeem_points = redeem_points.unwrap_or(0);
+    if redeem_points < 0 {
+        return Ok(CreateOrderResult::failed(
+            "จำนวนแต้มที่ใช้ต้องไม่ติดลบ".to_string(),
+        ));
+    }
+    if let (Some(customer_id), Some(config)) = (customer_id, &loyalty) {
+        match customers::redemption_discount(&mut tx, config, customer_id, redeem_points).await {
+            Ok(d) => discount = d,
+            Err(e) => return Ok(CreateOrderResult::failed(e)),
+        }
+    } else if redeem_points > 0 {
+        return Ok(CreateOrderResult::failed("ต้องระบุลูกค้าก่อนใช้แต้ม".to_string()));
+    }
+
+    let mut coupon = None;
+    if let Some(code) = coupon_code.as_deref().filter(|c| !c.trim().is_empty()) {
+        // A share carries no lines of its own to check the coupon against.
+        if tab_ways > 1 {
+            return Ok(CreateOrderResult::failed(
+                "ใช้คูปองกับบิลที่แบ่งจ่ายไม่ได้".to_string(),
+            ));
+        }
+        match coupons::evaluate(&mut tx, code, &items, customer_id, true).await {
+            Ok((code, coupon_discount)) => {
+                discount += coupon_discount;
+                coupon = Some((code, coupon_discount));
+            }
+            Err(e) => return Ok(CreateOrderResult::failed(e)),
+        }
+    }
+
+    // The total is priced here from the checked lines (or the tab share) less
+    // the point and coupon discounts; the client's figure only has to agree
+    // with it.
+    let subtotal = tab_amount.unwrap_or_else(|| pricing::order_subtotal(&items));
+    if discount > subtotal + 0.005 {
+        return Ok(CreateOrderResult::failed(format!(
+            "ส่วนลด {:.2} บาท เกินยอดสั่งซื้อ {:.2} บาท",
+            discount, subtotal
+        )));
+    }
+    let net_total = ((subtotal - discount).max(0.0) * 100.0).round() / 100.0;
+    if (total - net_total).abs() > 0.005 {
+        return Ok(CreateOrderResult::failed(format!(
+            "ยอดรวมไม่ตรงกับรายการ (ควรเป็น {:.2} บาท)",
+            net_total
+        )));
+    }
+    let total = net_total;
+
+    // A tab's lines went to the kitchen as they were added, round by round.
+    let kitchen_status = if tab_id.is_some() { "served" } else { "queued" };
+
     if let Err(e) = sqlx::query(
-        "INSERT INTO orders (id, order_number, total, payment_method, cashier_id, cashier_name)
-         VALUES ($1, $2, $3, $4, $5, $6)",
+        "INSERT INTO orders (id, order_number, total, payment_method, cashier_id, cashier_name,
+                             customer_id, discount, gift_card_amount, order_type, table_id,
+                             tab_id, note, kitchen_status)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
     )
     .bind(&order_id)
     .bind(order_number)
@@ -108,36 +309,155 @@ pub async fn create_order(
     .bind(&payment_method)
     .bind(&cashier_id)
     .bind(&cashier_name)
+    .bind(customer_id)
+    .bind(discount)
+    .bind(gift_card.as_ref().map_or(0.0, |g| g.amount))
+    .bind(order_type)
+    .bind(table_id)
+    .bind(tab_id)
+    .bind(note.as_deref().map(str::trim).filter(|n| !n.is_empty()))
+    .bind(kitchen_status)
     .execute(&mut *tx)
     .await
     {
-        return Ok(CreateOrderResult {
-            success: false,
-            order_id: None,
-            order_number: None,
-            error: Some(format!("บันทึกออเดอร์ไม่สำเร็จ: {}", e)),
-        });
+        return Ok(CreateOrderResult::failed(format!(
+            "บันทึกออเดอร์ไม่สำเร็จ: {}",
+            e
+        )));
     }
 
     for item in &items {
+        let unit_cost = costing::unit_cost(&mut tx, item.item_id)
+            .await
+            .map_err(|e| e.to_string())?;
+
         if let Err(e) = sqlx::query(
-            "INSERT INTO order_items (order_id, item_id, item_name, qty, price)
-             VALUES ($1, $2, $3, $4, $5)",
+            "INSERT INTO order_items (order_id, item_id, item_name, qty, price, unit_cost, is_reward,
+                                      combo_id, combo_index, sold_by, kitchen_status)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
         )
         .bind(&order_id)
         .bind(item.item_id)
         .bind(&item.item_name)
         .bind(item.qty)
-        .bind(item.price)
+        .bind(if item.is_reward { 0.0 } else { item.price })
+        .bind(unit_cost)
+        .bind(item.is_reward)
+        .bind(item.combo_id)
+        .bind(item.combo_index)
+        .bind(if item.is_weighed() {
+            item.sold_by.as_str()
+        } else {
+            "unit"
+        })
+        .bind(kitchen_status)
         .execute(&mut *tx)
         .await
         {
-            return Ok(CreateOrderResult {
-                success: false,
-                order_id: None,
-                order_number: None,
-                error: Some(format!("บันทึกรายการไม่สำเร็จ: {}", e)),
-            });
+            return Ok(CreateOrderResult::failed(format!(
+                "บันทึกรายการไม่สำเร็จ: {}",
+                e
+            )));
+        }
+    }
+
+    // Ingredients come off stock with the sale. A tab's lines all arrive with
+    // its closing settlement, so a split tab is deducted once.
+    let reference = format!("order:{}", order_id);
+    for item in &items {
+        let lines = costing::recipe_lines(&mut tx, item.item_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        for (stock_item_id, qty, cost) in lines {
+            if let Err(e) = inventory::record_movement(
+                &mut tx,
+                stock_item_id,
+                -qty * item.qty,
+                "sale",
+                &reference,
+                cost,
+                &cashier_id,
+            )
+            .await
+            {
+                return Ok(CreateOrderResult::failed(format!("ปรับสต็อกไม่สำเร็จ: {}", e)));
+            }
+        }
+    }
+
+    if let Some(tab_id) = tab_id {
+        tables::record_tab_payment(&mut tx, tab_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    if let Some((code, coupon_discount)) = &coupon {
+        if let Err(e) =
+            coupons::record_redemption(&mut tx, code, &order_id, customer_id, *coupon_discount)
+                .await
+        {
+            return Ok(CreateOrderResult::failed(format!(
+                "บันทึกการใช้คูปองไม่สำเร็จ: {}",
+                e
+            )));
+        }
+    }
+
+    if let Some(tender) = &gift_card {
+        if let Err(e) = gift_cards::redeem(&mut tx, tender, &order_id, total, &cashier_id).await {
+            return Ok(CreateOrderResult::failed(e));
+        }
+    }
+
+    let stamps =
+        match stamps::apply_stamps(&mut tx, customer_id, &order_id, &items, &cashier_id).await {
+            Ok(outcome) => outcome,
+            Err(e) => return Ok(CreateOrderResult::failed(e)),
+        };
+
+    let mut points_earned = None;
+    if let (Some(customer_id), Some(config)) = (customer_id, &loyalty) {
+        let earned = customers::points_earned(config, total);
+
+        for (points, reason) in [(-redeem_points, "redeem"), (earned, "earn")] {
+            if let Err(e) = customers::add_points(
+                &mut tx,
+                customer_id,
+                Some(&order_id),
+                points,
+                reason,
+                &cashier_id,
+            )
+            .await
+            {
+                return Ok(CreateOrderResult::failed(format!(
+                    "บันทึกแต้มสะสมไม่สำเร็จ: {}",
+                    e
+                )));
+            }
+        }
+        points_earned = Some(earned);
+    }
+
+    let mut print_jobs = Vec::new();
+    if tab_id.is_none() {
+        match tickets::queue_order_tickets(&mut tx, &order_id).await {
+            Ok(job_ids) => print_jobs.extend(job_ids),
+            Err(e) => {
+                return Ok(CreateOrderResult::failed(format!(
+                    "เพิ่มงานพิมพ์ไม่สำเร็จ: {}",
+                    e
+                )))
+            }
+        }
+        match labels::queue_order_labels(&mut tx, &order_id).await {
+            Ok(job_ids) => print_jobs.extend(job_ids),
+            Err(e) => {
+                return Ok(CreateOrderResult::failed(format!(
+                    "เพิ่มงานพิมพ์ไม่สำเร็จ: {}",
+                    e
+                )))
+            }
         }
     }
 
@@ -147,7 +467,10 @@ pub async fn create_order(
         success: true,
         order_id: Some(order_id),
         order_number: Some(order_number),
+        points_earned,
+        stamps_earned: customer_id.map(|_| stamps.earned),
         error: None,
+        print_jobs,
     })
 }
 
@@ -160,7 +483,9 @@ pub async fn get_orders(
 
     sqlx::query_as::<_, OrderSummary>(
         "SELECT o.id, o.order_number, o.total, o.payment_method, o.cashier_name, o.created_at,
-                COALESCE(SUM(oi.qty), 0) AS item_count
+                COALESCE(SUM(CASE WHEN oi.sold_by = 'unit' THEN oi.qty ELSE 1 END), 0)::bigint
+                    AS item_count,
+                o.order_type, o.table_id
          FROM orders o
          LEFT JOIN order_items oi ON oi.order_id = o.id
          WHERE o.created_at::date = $1::date
@@ -176,10 +501,12 @@ pub async fn get_orders(
 #[tauri::command]
 pub async fn get_order_detail(
     order_id: String,
+    locale: Option<String>,
     db: State<'_, Database>,
 ) -> Result<Option<OrderDetail>, String> {
     let order = sqlx::query_as::<_, OrderDetail>(
-        "SELECT id, order_number, total, payment_method, cashier_id, cashier_name, created_at
+        "SELECT id, order_number, total, payment_method, cashier_id, cashier_name, created_at,
+                order_type, table_id, note
          FROM orders WHERE id = $1",
     )
     .bind(&order_id)
@@ -190,14 +517,72 @@ pub async fn get_order_detail(
     match order {
         Some(mut detail) => {
             detail.items = sqlx::query_as::<_, OrderItemDetail>(
-                "SELECT item_id, item_name, qty, price, (qty::double precision * price) AS subtotal
-                 FROM order_items WHERE order_id = $1",
+                "SELECT item_id, item_name, qty, sold_by, price, (qty * price) AS subtotal,
+                        combo_id, combo_index
+                 FROM order_items WHERE order_id = $1
+                 ORDER BY id",
             )
             .bind(&order_id)
             .fetch_all(&db.pool)
             .await
             .map_err(|e| e.to_string())?;
 
+            let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+            // Receipts in the customer's language; `item_name` keeps the name
+            // saved at sale time when there is no translation.
+            if let Some(names) =
+                translations::for_locale(&mut conn, "menu_item", locale.as_deref()).await?
+            {
+                let english = locale.as_deref().map(str::trim) == Some("en");
+                let item_ids: Vec<i32> = detail.items.iter().map(|i| i.item_id).collect();
+                let names_en: HashMap<i32, String> = sqlx::query_as::<_, (i32, String)>(
+                    "SELECT id, name_en FROM menu_items WHERE id = ANY($1)",
+                )
+                .bind(&item_ids)
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .collect();
+
+                for item in &mut detail.items {
+                    if let Some(t) = names.get(&item.item_id.to_string()) {
+                        item.item_name = t.name.clone();
+                    } else if let Some(name_en) = names_en.get(&item.item_id).filter(|_| english) {
+                        item.item_name = name_en.clone();
+                    }
+                }
+            }
+
+            let config = dietary::print_config(&mut conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            if config.show_dietary_info {
+                let item_ids: Vec<i32> = detail.items.iter().map(|i| i.item_id).collect();
+                let notes: HashMap<i32, String> =
+                    sqlx::query_as::<_, (i32, Vec<String>, Vec<String>, Option<i32>)>(
+                        "SELECT id, allergens, dietary_flags, calories
+                         FROM menu_items WHERE id = ANY($1)",
+                    )
+                    .bind(&item_ids)
+                    .fetch_all(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .into_iter()
+                    .map(|(id, allergens, flags, calories)| {
+                        (id, dietary::dietary_note(&allergens, &flags, calories))
+                    })
+                    .collect();
+
+                for item in &mut detail.items {
+                    item.dietary_note = notes
+                        .get(&item.item_id)
+                        .filter(|note| !note.is_empty())
+                        .cloned();
+                }
+            }
+
             Ok(Some(detail))
         }
         None => Ok(None),
@@ -211,13 +596,16 @@ pub async fn get_daily_summary(
 ) -> Result<DailySummary, String> {
     let target = date.unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
 
-    let row: (i64, f64, f64, f64, f64) = sqlx::query_as(
+    // Amounts paid by gift card are taken out of the other tenders so the
+    // cash/promptpay/card totals match what was actually collected.
+    let row: (i64, f64, f64, f64, f64, f64) = sqlx::query_as(
         "SELECT
             COUNT(*)::bigint,
             COALESCE(SUM(total), 0),
-            COALESCE(SUM(total) FILTER (WHERE payment_method = 'cash'), 0),
-            COALESCE(SUM(total) FILTER (WHERE payment_method = 'promptpay'), 0),
-            COALESCE(SUM(total) FILTER (WHERE payment_method = 'card'), 0)
+            COALESCE(SUM(total - gift_card_amount) FILTER (WHERE payment_method = 'cash'), 0),
+            COALESCE(SUM(total - gift_card_amount) FILTER (WHERE payment_method = 'promptpay'), 0),
+            COALESCE(SUM(total - gift_card_amount) FILTER (WHERE payment_method = 'card'), 0),
+            COALESCE(SUM(gift_card_amount), 0)
          FROM orders
          WHERE created_at::date = $1::date",
     )
@@ -226,6 +614,18 @@ pub async fn get_daily_summary(
     .await
     .map_err(|e| e.to_string())?;
 
+    // Gift cards sold are a liability, not revenue; revenue is recognised
+    // when the card is redeemed against an order.
+    let (gift_card_sales,): (f64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0)
+         FROM gift_card_transactions
+         WHERE kind = 'issue' AND created_at::date = $1::date",
+    )
+    .bind(&target)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
     Ok(DailySummary {
         date: target,
         total_orders: row.0,
@@ -233,5 +633,7 @@ pub async fn get_daily_summary(
         cash_total: row.2,
         promptpay_total: row.3,
         card_total: row.4,
+        gift_card_redeemed: row.5,
+        gift_card_sales,
     })
 }
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::orders::OrderItemInput;
+
+// ── Request / Response types ──
+
+/// A schedule that overrides base menu prices. Empty `weekdays` (ISO, 1 = Mon
+/// … 7 = Sun) and missing times or dates mean "no restriction". A time range
+/// whose end is before its start runs past midnight.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PriceList {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub weekdays: Vec<i32>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub is_active: bool,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub entries: Vec<PriceListEntry>,
+}
+
+/// Targets either one menu item or a whole category, and sets either a
+/// percentage off or a fixed price.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PriceListEntry {
+    pub menu_item_id: Option<i32>,
+    pub category_id: Option<String>,
+    pub percent_off: Option<f64>,
+    pub fixed_price: Option<f64>,
+}
+
+#[derive(FromRow)]
+struct EntryRow {
+    price_list_id: i32,
+    #[sqlx(flatten)]
+    entry: PriceListEntry,
+}
+
+#[derive(FromRow)]
+struct MenuPriceRow {
+    id: i32,
+    category_id: String,
+    price: f64,
+    sold_by: String,
+}
+
+/// Price lists that are active right now, highest priority first.
+pub(crate) struct ActivePrices {
+    lists: Vec<PriceList>,
+}
+
+// ── Helpers ──
+
+impl PriceList {
+    fn applies_at<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> bool {
+        let date = now.date_naive();
+        let time = now.time();
+
+        if !self.weekdays.is_empty()
+            && !self
+                .weekdays
+                .contains(&(now.weekday().number_from_monday() as i32))
+        {
+            return false;
+        }
+        if self.start_date.is_some_and(|d| date < d) || self.end_date.is_some_and(|d| date > d) {
+            return false;
+        }
+
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) if start <= end => time >= start && time < end,
+            (Some(start), Some(end)) => time >= start || time < end,
+            (Some(start), None) => time >= start,
+            (None, Some(end)) => time < end,
+            (None, None) => true,
+        }
+    }
+}
+
+impl ActivePrices {
+    /// Effective price of one unit. Within a list an item entry beats a
+    /// category entry; across lists the higher priority wins.
+    pub(crate) fn price_for(&self, item_id: i32, category_id: &str, base_price: f64) -> f64 {
+        let entry = self.lists.iter().find_map(|list| {
+            list.entries
+                .iter()
+                .find(|e| e.menu_item_id == Some(item_id))
+                .or_else(|| {
+                    list.entries
+                        .iter()
+                        .find(|e| e.category_id.as_deref() == Some(category_id))
+                })
+        });
+
+        match entry {
+            Some(PriceListEntry {
+                fixed_price: Some(price),
+                ..
+            }) => *price,
+            Some(PriceListEntry {
+                percent_off: Some(pct),
+                ..
+            }) => ((base_price * (100.0 - pct) / 100.0) * 100.0).round() / 100.0,
+            _ => base_price,
+        }
+    }
+}
+
+async fn load_price_lists(
+    conn: &mut PgConnection,
+    active_only: bool,
+) -> Result<Vec<PriceList>, sqlx::Error> {
+    let mut lists = sqlx::query_as::<_, PriceList>(
+        "SELECT id, name, priority, weekdays, start_time, end_time, start_date, end_date, is_active
+         FROM price_lists
+         WHERE is_active OR NOT $1
+         ORDER BY priority DESC, id",
+    )
+    .bind(active_only)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let entries = sqlx::query_as::<_, EntryRow>(
+        "SELECT price_list_id, menu_item_id, category_id, percent_off, fixed_price
+         FROM price_list_entries
+         ORDER BY id",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut by_list: HashMap<i32, Vec<PriceListEntry>> = HashMap::new();
+    for row in entries {
+        by_list
+            .entry(row.price_list_id)
+            .or_default()
+            .push(row.entry);
+    }
+    for list in &mut lists {
+        list.entries = by_list.remove(&list.id).unwrap_or_default();
+    }
+
+    Ok(lists)
+}
+
+pub(crate) async fn active_prices(conn: &mut PgConnection) -> Result<ActivePrices, sqlx::Error> {
+    let now = Local::now();
+    let lists = load_price_lists(conn, true)
+        .await?
+        .into_iter()
+        .filter(|l| l.applies_at(&now))
+        .collect();
+
+    Ok(ActivePrices { lists })
+}
+
+/// Rejects a cart whose prices no longer match the effective price, e.g. a
+/// happy-hour cart checked out after the happy hour ended. Also checks the
+/// quantities: whole pieces for items sold by the unit, any positive weight
+/// for weighed ones, and copies `sold_by` onto each line.
+pub(crate) async fn check_order_prices(
+    conn: &mut PgConnection,
+    items: &mut [OrderItemInput],
+) -> Result<(), String> {
+    let prices = active_prices(&mut *conn).await.map_err(|e| e.to_string())?;
+
+    let item_ids: Vec<i32> = items.iter().map(|i| i.item_id).collect();
+    let menu: HashMap<i32, MenuPriceRow> = sqlx::query_as::<_, MenuPriceRow>(
+        "SELECT id, category_id, price, sold_by FROM menu_items WHERE id = ANY($1)",
+    )
+    .bind(&item_ids)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .map(|row| (row.id, row))
+    .collect();
+
+    for item in items.iter_mut() {
+        let row = menu
+            .get(&item.item_id)
+            .ok_or_else(|| format!("ไม่พบเมนู {}", item.item_name))?;
+        item.sold_by = row.sold_by.clone();
+
+        if item.qty <= 0.0 {
+            return Err(format!("จำนวน {} ต้องมากกว่า 0", item.item_name));
+        }
+        if item.is_weighed() {
+            if item.is_reward {
+                return Err(format!("{} ขายตามน้ำหนัก แลกแสตมป์ไม่ได้", item.item_name));
+            }
+        } else if item.qty.fract() != 0.0 {
+            return Err(format!("จำนวน {} ต้องเป็นจำนวนเต็ม", item.item_name));
+        }
+
+        if item.is_reward {
+            continue;
+        }
+        let expected = prices.price_for(item.item_id, &row.category_id, row.price);
+        if (item.price - expected).abs() > 0.005 {
+            return Err(format!(
+                "ราคา {} เปลี่ยนเป็น {:.2} บาท กรุณาโหลดเมนูใหม่",
+                item.item_name, expected
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// What the lines add up to, rounded to satang. Reward lines are free.
+pub(crate) fn order_subtotal(items: &[OrderItemInput]) -> f64 {
+    let sum: f64 = items
+        .iter()
+        .filter(|i| !i.is_reward)
+        .map(|i| i.price * i.qty)
+        .sum();
+
+    (sum * 100.0).round() / 100.0
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_price_lists(db: State<'_, Database>) -> Result<Vec<PriceList>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    load_price_lists(&mut conn, false)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Creates a price list when `id` is 0, otherwise replaces it and its entries.
+#[tauri::command]
+pub async fn save_price_list(
+    price_list: PriceList,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    if price_list.weekdays.iter().any(|d| !(1..=7).contains(d)) {
+        return Err("วันในสัปดาห์ต้องอยู่ระหว่าง 1 (จันทร์) ถึง 7 (อาทิตย์)".to_string());
+    }
+    for entry in &price_list.entries {
+        if entry.menu_item_id.is_some() == entry.category_id.is_some() {
+            return Err("แต่ละรายการต้องระบุเมนูหรือหมวดหมู่อย่างใดอย่างหนึ่ง".to_string());
+        }
+        match (entry.percent_off, entry.fixed_price) {
+            (Some(pct), None) if (0.0..=100.0).contains(&pct) => {}
+            (None, Some(price)) if price >= 0.0 => {}
+            _ => return Err("ต้องระบุส่วนลด (%) หรือราคาคงที่อย่างใดอย่างหนึ่ง".to_string()),
+        }
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let id = if price_list.id == 0 {
+        let (id,): (i32,) = sqlx::query_as(
+            "INSERT INTO price_lists (name, priority, weekdays, start_time, end_time,
+                                      start_date, end_date, is_active)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             RETURNING id",
+        )
+        .bind(&price_list.name)
+        .bind(price_list.priority)
+        .bind(&price_list.weekdays)
+        .bind(price_list.start_time)
+        .bind(price_list.end_time)
+        .bind(price_list.start_date)
+        .bind(price_list.end_date)
+        .bind(price_list.is_active)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกรายการราคาไม่สำเร็จ: {}", e))?;
+        id
+    } else {
+        let rows = sqlx::query(
+            "UPDATE price_lists
+             SET name = $1, priority = $2, weekdays = $3, start_time = $4, end_time = $5,
+                 start_date = $6, end_date = $7, is_active = $8
+             WHERE id = $9",
+        )
+        .bind(&price_list.name)
+        .bind(price_list.priority)
+        .bind(&price_list.weekdays)
+        .bind(price_list.start_time)
+        .bind(price_list.end_time)
+        .bind(price_list.start_date)
+        .bind(price_list.end_date)
+        .bind(price_list.is_active)
+        .bind(price_list.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกรายการราคาไม่สำเร็จ: {}", e))?
+        .rows_affected();
+        if rows == 0 {
+            return Err("ไม่พบรายการราคา".to_string());
+        }
+
+        sqlx::query("DELETE FROM price_list_entries WHERE price_list_id = $1")
+            .bind(price_list.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        price_list.id
+    };
+
+    for entry in &price_list.entries {
+        sqlx::query(
+            "INSERT INTO price_list_entries
+                 (price_list_id, menu_item_id, category_id, percent_off, fixed_price)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(id)
+        .bind(entry.menu_item_id)
+        .bind(&entry.category_id)
+        .bind(entry.percent_off)
+        .bind(entry.fixed_price)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกรายการราคาไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn delete_price_list(id: i32, db: State<'_, Database>) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM price_list_entries WHERE price_list_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rows = sqlx::query("DELETE FROM price_lists WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("ลบรายการราคาไม่สำเร็จ: {}", e))?
+        .rows_affected();
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(rows > 0)
+}
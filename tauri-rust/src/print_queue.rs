@@ -0,0 +1,382 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgPool};
+use tauri::{AppHandle, Manager, State};
+
+use crate::database::Database;
+use crate::printer;
+use crate::printers::{self, Printer};
+
+pub const JOB_KINDS: [&str; 4] = ["receipt", "ticket", "label", "test"];
+pub const JOB_STATUSES: [&str; 4] = ["queued", "sent", "failed", "cancelled"];
+
+/// Frontend event carrying a `PrintJob` whenever its status or attempt count
+/// changes.
+pub const JOB_EVENT: &str = "print-job";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const FIRST_RETRY_SECONDS: f64 = 5.0;
+const MAX_RETRY_SECONDS: f64 = 300.0;
+/// About 20 minutes of retries before a job is marked failed.
+const MAX_ATTEMPTS: i32 = 10;
+/// Sent and cancelled jobs are kept this long for the job list.
+const KEEP_DAYS: i32 = 7;
+
+const JOB_COLUMNS: &str = "j.id, j.printer_id,
+     COALESCE(p.name, j.host || ':' || j.port) AS printer_name,
+     j.kind, j.order_id, j.status, j.attempts, j.last_error, j.next_attempt_at,
+     j.created_at, j.sent_at";
+
+// ── Request / Response types ──
+
+/// A print job as shown to the frontend; the payload itself is not sent.
+/// Jobs go to a registered printer, or straight to `host:port` for printers
+/// that are only configured on the terminal.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PrintJob {
+    pub id: i32,
+    pub printer_id: Option<i32>,
+    pub printer_name: Option<String>,
+    pub kind: String,
+    pub order_id: Option<String>,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+struct DueJob {
+    id: i32,
+    printer_id: Option<i32>,
+    host: Option<String>,
+    port: Option<i32>,
+    kind: String,
+    payload: Vec<u8>,
+    attempts: i32,
+}
+
+// ── Helpers ──
+
+/// Queues raw printer data for a registered printer. Call it inside the
+/// transaction that creates whatever is being printed, so the job only exists
+/// once that is committed.
+pub(crate) async fn enqueue(
+    conn: &mut PgConnection,
+    printer_id: i32,
+    kind: &str,
+    order_id: Option<&str>,
+    payload: &[u8],
+) -> Result<i32, sqlx::Error> {
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO print_jobs (printer_id, kind, order_id, payload)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id",
+    )
+    .bind(printer_id)
+    .bind(kind)
+    .bind(order_id)
+    .bind(payload)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(id)
+}
+
+async fn load_job(conn: &mut PgConnection, id: i32) -> Result<Option<PrintJob>, sqlx::Error> {
+    sqlx::query_as::<_, PrintJob>(&format!(
+        "SELECT {}
+         FROM print_jobs j
+         LEFT JOIN printers p ON p.id = j.printer_id
+         WHERE j.id = $1",
+        JOB_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await
+}
+
+async fn emit_job(app: &AppHandle, pool: &PgPool, id: i32) {
+    if let Ok(mut conn) = pool.acquire().await {
+        if let Ok(Some(job)) = load_job(&mut conn, id).await {
+            let _ = app.emit_all(JOB_EVENT, job);
+        }
+    }
+}
+
+/// 5 s, 10 s, 20 s … up to 5 minutes between attempts.
+fn retry_delay(attempts: i32) -> f64 {
+    (FIRST_RETRY_SECONDS * 2f64.powi(attempts - 1)).min(MAX_RETRY_SECONDS)
+}
+
+/// Asks an ESC/POS network printer for its status before a receipt or ticket
+/// goes out. The printer accepts data while out of paper or with the cover
+/// open and then loses it, so the job is retried later instead. Printers
+/// that don't answer status queries are sent to as before.
+async fn check_ready(address: String) -> Result<(), String> {
+    let status = tokio::task::spawn_blocking(move || printer::network_printer_status(&address))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match status {
+        Some(status) if status.blocks_printing() => {
+            Err(format!("เครื่องพิมพ์ไม่พร้อม: {}", status.describe()))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn send_job(conn: &mut PgConnection, job: &DueJob) -> Result<(), String> {
+    let escpos = job.kind == "receipt" || job.kind == "ticket";
+
+    let Some(printer_id) = job.printer_id else {
+        let address = format!(
+            "{}:{}",
+            job.host.as_deref().unwrap_or_default(),
+            job.port.unwrap_or(printers::DEFAULT_PORT)
+        );
+        if escpos {
+            check_ready(address.clone()).await?;
+        }
+        return printers::send_tcp(&address, &job.payload).await;
+    };
+
+    let printer = sqlx::query_as::<_, Printer>(
+        "SELECT id, name, role, connection, host, port, queue_name, thai_codepage,
+                label_language, label_width_mm, label_height_mm, is_active
+         FROM printers
+         WHERE id = $1",
+    )
+    .bind(printer_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match printer {
+        Some(printer) if printer.is_active => {
+            if let Some(address) = printers::network_address(&printer).filter(|_| escpos) {
+                check_ready(address).await?;
+            }
+            printers::send(&printer, &job.payload).await
+        }
+        Some(printer) => Err(format!("เครื่องพิมพ์ {} ถูกปิดใช้งาน", printer.name)),
+        None => Err("ไม่พบเครื่องพิมพ์".to_string()),
+    }
+}
+
+/// Sends the oldest due job, if any. The row stays locked while it is sent,
+/// so terminals sharing the database never print the same job twice.
+async fn process_next(pool: &PgPool) -> Result<Option<i32>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let job = sqlx::query_as::<_, DueJob>(
+        "SELECT id, printer_id, host, port, kind, payload, attempts
+         FROM print_jobs
+         WHERE status = 'queued' AND next_attempt_at <= NOW()
+         ORDER BY next_attempt_at, id
+         LIMIT 1
+         FOR UPDATE SKIP LOCKED",
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(job) = job else {
+        return Ok(None);
+    };
+
+    let attempts = job.attempts + 1;
+    match send_job(&mut tx, &job).await {
+        Ok(()) => {
+            sqlx::query(
+                "UPDATE print_jobs
+                 SET status = 'sent', attempts = $1, last_error = NULL, sent_at = NOW()
+                 WHERE id = $2",
+            )
+            .bind(attempts)
+            .bind(job.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Err(e) => {
+            sqlx::query(
+                "UPDATE print_jobs
+                 SET status = CASE WHEN $1 >= $2 THEN 'failed' ELSE 'queued' END,
+                     attempts = $1, last_error = $3,
+                     next_attempt_at = NOW() + $4 * INTERVAL '1 second'
+                 WHERE id = $5",
+            )
+            .bind(attempts)
+            .bind(MAX_ATTEMPTS)
+            .bind(&e)
+            .bind(retry_delay(attempts))
+            .bind(job.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(job.id))
+}
+
+/// Background worker started with the app. Sends due jobs one at a time and
+/// polls again when there is nothing to do or the database is unreachable.
+pub async fn run(app: AppHandle, pool: PgPool) {
+    let _ = sqlx::query(
+        "DELETE FROM print_jobs
+         WHERE status IN ('sent', 'cancelled') AND created_at < NOW() - $1 * INTERVAL '1 day'",
+    )
+    .bind(KEEP_DAYS)
+    .execute(&pool)
+    .await;
+
+    loop {
+        match process_next(&pool).await {
+            Ok(Some(id)) => emit_job(&app, &pool, id).await,
+            Ok(None) | Err(_) => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+// ── Commands ──
+
+/// Queues raw printer data, e.g. a receipt rendered by the frontend. Goes to
+/// `printer_id` when given, otherwise to the terminal's network printer at
+/// `printer_ip:printer_port`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn enqueue_print_job(
+    printer_id: Option<i32>,
+    printer_ip: Option<String>,
+    printer_port: Option<String>,
+    kind: Option<String>,
+    order_id: Option<String>,
+    data: Vec<u8>,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    let kind = kind.unwrap_or_else(|| "receipt".to_string());
+    if !JOB_KINDS.contains(&kind.as_str()) {
+        return Err(format!("ประเภทงานพิมพ์ต้องเป็น {}", JOB_KINDS.join(", ")));
+    }
+    if data.is_empty() {
+        return Err("ไม่มีข้อมูลที่จะพิมพ์".to_string());
+    }
+
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    if let Some(printer_id) = printer_id {
+        return enqueue(&mut conn, printer_id, &kind, order_id.as_deref(), &data)
+            .await
+            .map_err(|e| format!("เพิ่มงานพิมพ์ไม่สำเร็จ: {}", e));
+    }
+
+    let host = printer_ip
+        .as_deref()
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .ok_or_else(|| "กรุณาระบุเครื่องพิมพ์".to_string())?;
+    let port = match printer_port.as_deref().map(str::trim) {
+        None | Some("") => printers::DEFAULT_PORT,
+        Some(port) => port
+            .parse::<i32>()
+            .map_err(|_| format!("พอร์ตไม่ถูกต้อง: {}", port))?,
+    };
+
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO print_jobs (host, port, kind, order_id, payload)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id",
+    )
+    .bind(host)
+    .bind(port)
+    .bind(&kind)
+    .bind(&order_id)
+    .bind(&data)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("เพิ่มงานพิมพ์ไม่สำเร็จ: {}", e))?;
+
+    Ok(id)
+}
+
+/// Jobs that are still queued or have failed, plus today's other jobs,
+/// newest first. `status` narrows the list to one status.
+#[tauri::command]
+pub async fn get_print_jobs(
+    status: Option<String>,
+    db: State<'_, Database>,
+) -> Result<Vec<PrintJob>, String> {
+    if let Some(status) = status.as_deref() {
+        if !JOB_STATUSES.contains(&status) {
+            return Err(format!("สถานะต้องเป็น {}", JOB_STATUSES.join(", ")));
+        }
+    }
+
+    sqlx::query_as::<_, PrintJob>(&format!(
+        "SELECT {}
+         FROM print_jobs j
+         LEFT JOIN printers p ON p.id = j.printer_id
+         WHERE ($1::text IS NULL OR j.status = $1)
+           AND (j.status IN ('queued', 'failed') OR j.created_at >= CURRENT_DATE)
+         ORDER BY j.id DESC",
+        JOB_COLUMNS
+    ))
+    .bind(&status)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Queues a failed or cancelled job again with a fresh set of attempts.
+#[tauri::command]
+pub async fn retry_print_job(
+    id: i32,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let result = sqlx::query(
+        "UPDATE print_jobs
+         SET status = 'queued', attempts = 0, next_attempt_at = NOW()
+         WHERE id = $1 AND status IN ('failed', 'cancelled')",
+    )
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err("ลองพิมพ์ใหม่ได้เฉพาะงานที่ล้มเหลวหรือถูกยกเลิก".to_string());
+    }
+
+    emit_job(&app, &db.pool, id).await;
+
+    Ok(true)
+}
+
+/// Stops a job that has not been sent yet.
+#[tauri::command]
+pub async fn cancel_print_job(
+    id: i32,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let result = sqlx::query(
+        "UPDATE print_jobs
+         SET status = 'cancelled'
+         WHERE id = $1 AND status IN ('queued', 'failed')",
+    )
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err("ยกเลิกได้เฉพาะงานที่ยังไม่ได้พิมพ์".to_string());
+    }
+
+    emit_job(&app, &db.pool, id).await;
+
+    Ok(true)
+}
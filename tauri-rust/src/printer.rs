@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command;
-use std::net::TcpStream;
+use std::net::{TcpStream, ToSocketAddrs};
 use std::time::Duration;
-use std::io::Write;
+use std::io::{Read, Write};
 use chrono::Local;
 
 // Global mock mode flag removed
@@ -12,6 +12,58 @@ pub struct PrinterInfo {
     pub name: String,
     pub is_default: bool,
     pub status: String,
+    // Only set for network printers that answer ESC/POS status queries
+    pub printer_status: Option<PrinterStatus>,
+}
+
+/// Real-time status of an ESC/POS printer, read with DLE EOT 1-4
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrinterStatus {
+    pub offline: bool,
+    pub cover_open: bool,
+    pub paper_near_end: bool,
+    pub paper_end: bool,
+    pub cutter_error: bool,
+    pub unrecoverable_error: bool,
+}
+
+impl PrinterStatus {
+    // Bytes are the answers to DLE EOT 1 (printer), 2 (offline cause),
+    // 3 (error cause) and 4 (roll paper sensor)
+    fn from_bytes([printer, offline, error, paper]: [u8; 4]) -> Self {
+        PrinterStatus {
+            offline: printer & 0x08 != 0,
+            cover_open: offline & 0x04 != 0,
+            paper_near_end: paper & 0x0C != 0,
+            paper_end: paper & 0x60 != 0 || offline & 0x20 != 0,
+            cutter_error: error & 0x08 != 0,
+            unrecoverable_error: error & 0x20 != 0,
+        }
+    }
+
+    /// Whether a job sent now would be lost: out of paper, cover open or offline
+    pub fn blocks_printing(&self) -> bool {
+        self.paper_end || self.cover_open || self.offline
+    }
+
+    /// Short message for the cashier, most urgent problem first
+    pub fn describe(&self) -> String {
+        if self.paper_end {
+            "กระดาษหมด".to_string()
+        } else if self.cover_open {
+            "ฝาเครื่องพิมพ์เปิดอยู่".to_string()
+        } else if self.cutter_error {
+            "ใบมีดตัดกระดาษขัดข้อง".to_string()
+        } else if self.unrecoverable_error {
+            "เครื่องพิมพ์ขัดข้อง".to_string()
+        } else if self.offline {
+            "ออฟไลน์".to_string()
+        } else if self.paper_near_end {
+            "กระดาษใกล้หมด".to_string()
+        } else {
+            "Online".to_string()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,11 +77,23 @@ pub struct PrinterCheckResult {
 
 /// Check for connected printers using system commands
 #[tauri::command]
-pub fn check_printer_connection(
+pub async fn check_printer_connection(
     printer_ip: Option<String>,
     printer_port: Option<String>,
 ) -> PrinterCheckResult {
+    // lpstat and the ESC/POS status queries block for seconds at a time, so
+    // they run off the main thread
+    tokio::task::spawn_blocking(move || check_printers(printer_ip, printer_port))
+        .await
+        .unwrap_or_else(|e| PrinterCheckResult {
+            connected: false,
+            printers: vec![],
+            default_printer: None,
+            error: Some(e.to_string()),
+        })
+}
 
+fn check_printers(printer_ip: Option<String>, printer_port: Option<String>) -> PrinterCheckResult {
     #[cfg(target_os = "linux")]
     {
         check_printers_linux(printer_ip, printer_port)
@@ -53,15 +117,59 @@ fn check_network_printer(ip: &str, port: &str) -> Option<PrinterInfo> {
         &address.parse().unwrap_or_else(|_| "0.0.0.0:0".parse().unwrap()),
         Duration::from_secs(2),
     ) {
-        Ok(_) => Some(PrinterInfo {
-            name: format!("Network Printer ({})", address),
-            is_default: true,
-            status: "Online".to_string(),
-        }),
+        Ok(mut stream) => {
+            // Printers that don't speak ESC/POS (label printers, some print
+            // servers) never answer, so they still just show as online
+            let printer_status = stream
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .and_then(|_| stream.set_write_timeout(Some(Duration::from_secs(2))))
+                .ok()
+                .and_then(|_| query_printer_status(&mut stream));
+            let status = printer_status
+                .as_ref()
+                .map_or_else(|| "Online".to_string(), PrinterStatus::describe);
+
+            Some(PrinterInfo {
+                name: format!("Network Printer ({})", address),
+                is_default: true,
+                status,
+                printer_status,
+            })
+        }
         Err(_) => None,
     }
 }
 
+/// Status of the ESC/POS printer at `address` (host:port), or None when it
+/// can't be reached or doesn't answer. Blocks for a few seconds at most
+pub(crate) fn network_printer_status(address: &str) -> Option<PrinterStatus> {
+    let addr = address.to_socket_addrs().ok()?.next()?;
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(2)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(2))).ok()?;
+    stream.set_write_timeout(Some(Duration::from_secs(2))).ok()?;
+    query_printer_status(&mut stream)
+}
+
+// Sends DLE EOT 1-4 and reads one answer byte for each. The caller sets the
+// timeouts, so a printer that never answers gives None instead of hanging
+fn query_printer_status<S: Read + Write>(stream: &mut S) -> Option<PrinterStatus> {
+    let mut bytes = [0u8; 4];
+    for (n, byte) in (1u8..=4).zip(bytes.iter_mut()) {
+        // DLE EOT n
+        stream.write_all(&[0x10, 0x04, n]).ok()?;
+        let mut answer = [0u8; 1];
+        stream.read_exact(&mut answer).ok()?;
+        // Every status byte has bits 1 and 4 set and bits 0 and 7 clear;
+        // anything else is not an answer to our query
+        if answer[0] & 0x93 != 0x12 {
+            return None;
+        }
+        *byte = answer[0];
+    }
+
+    Some(PrinterStatus::from_bytes(bytes))
+}
+
 #[cfg(target_os = "linux")]
 fn check_printers_linux(ip: Option<String>, port: Option<String>) -> PrinterCheckResult {
     // Use lpstat to check for printers on Linux
@@ -132,6 +240,7 @@ fn check_printers_linux(ip: Option<String>, port: Option<String>) -> PrinterChec
                             name: name.clone(),
                             is_default: false,
                             status,
+                            printer_status: None,
                         });
                     }
                 }
@@ -201,6 +310,7 @@ fn check_printers_macos(ip: Option<String>, port: Option<String>) -> PrinterChec
                             name: name.clone(),
                             is_default: false,
                             status: "พร้อมใช้งาน".to_string(),
+                            printer_status: None,
                         });
                     }
                 }
@@ -279,6 +389,7 @@ fn check_printers_windows(ip: Option<String>, port: Option<String>) -> PrinterCh
                         name,
                         is_default,
                         status: "พร้อมใช้งาน".to_string(),
+                        printer_status: None,
                     });
                 }
             }
@@ -332,14 +443,27 @@ pub fn test_print(
 fn test_print_network(ip: &str, port: &str) -> TestPrintResult {
     let address = format!("{}:{}", ip, port);
     
-    // Connect to printer
-    let mut stream = match TcpStream::connect(&address) {
+    // Connect to printer, giving up after a few seconds instead of hanging on
+    // an unreachable address
+    let socket_addr = match address.to_socket_addrs().map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => addr,
+        Ok(None) => return TestPrintResult {
+            success: false,
+            message: format!("ไม่พบที่อยู่เครื่องพิมพ์ {}", address),
+        },
+        Err(e) => return TestPrintResult {
+            success: false,
+            message: format!("ไม่สามารถเชื่อมต่อเครื่องพิมพ์ {}: {}", address, e),
+        },
+    };
+    let mut stream = match TcpStream::connect_timeout(&socket_addr, Duration::from_secs(3)) {
         Ok(s) => s,
         Err(e) => return TestPrintResult {
             success: false,
             message: format!("ไม่สามารถเชื่อมต่อเครื่องพิมพ์ {}: {}", address, e),
         },
     };
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(10)));
 
     // ESC/POS commands
     // ESC @ = Initialize
@@ -510,3 +634,124 @@ Status: SUCCESSFUL
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    // Answers each DLE EOT n with answers[n - 1]; `sent` keeps the queries
+    struct FakePrinter {
+        answers: Vec<u8>,
+        sent: Vec<u8>,
+        pending: Option<u8>,
+    }
+
+    impl FakePrinter {
+        fn new(answers: &[u8]) -> Self {
+            FakePrinter { answers: answers.to_vec(), sent: Vec::new(), pending: None }
+        }
+    }
+
+    impl Read for FakePrinter {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.pending.take() {
+                Some(byte) => {
+                    buf[0] = byte;
+                    Ok(1)
+                }
+                None => Err(io::Error::new(io::ErrorKind::TimedOut, "no answer")),
+            }
+        }
+    }
+
+    impl Write for FakePrinter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.extend_from_slice(buf);
+            if let [0x10, 0x04, n] = buf {
+                self.pending = self.answers.get(*n as usize - 1).copied();
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Idle answers to DLE EOT 1-4: only the fixed bits 1 and 4 set
+    const IDLE: [u8; 4] = [0x12, 0x12, 0x12, 0x12];
+
+    fn status(answers: [u8; 4]) -> Option<PrinterStatus> {
+        query_printer_status(&mut FakePrinter::new(&answers))
+    }
+
+    #[test]
+    fn idle_printer_is_online() {
+        let status = status(IDLE).unwrap();
+        assert!(!status.paper_end && !status.paper_near_end && !status.cover_open);
+        assert!(!status.offline && !status.cutter_error && !status.unrecoverable_error);
+        assert_eq!(status.describe(), "Online");
+    }
+
+    #[test]
+    fn paper_end() {
+        let status = status([0x12, 0x12, 0x12, 0x72]).unwrap();
+        assert!(status.paper_end);
+        assert_eq!(status.describe(), "กระดาษหมด");
+    }
+
+    #[test]
+    fn paper_near_end() {
+        let status = status([0x12, 0x12, 0x12, 0x1E]).unwrap();
+        assert!(status.paper_near_end);
+        assert!(!status.paper_end);
+        assert_eq!(status.describe(), "กระดาษใกล้หมด");
+    }
+
+    #[test]
+    fn cover_open() {
+        let status = status([0x1A, 0x16, 0x12, 0x12]).unwrap();
+        assert!(status.cover_open);
+        assert!(status.offline);
+        assert_eq!(status.describe(), "ฝาเครื่องพิมพ์เปิดอยู่");
+    }
+
+    #[test]
+    fn paper_end_cover_open_and_offline_block_printing() {
+        assert!(!status(IDLE).unwrap().blocks_printing());
+        assert!(!status([0x12, 0x12, 0x12, 0x1E]).unwrap().blocks_printing());
+        assert!(status([0x12, 0x12, 0x12, 0x72]).unwrap().blocks_printing());
+        assert!(status([0x1A, 0x16, 0x12, 0x12]).unwrap().blocks_printing());
+        assert!(status([0x1A, 0x12, 0x12, 0x12]).unwrap().blocks_printing());
+    }
+
+    #[test]
+    fn cutter_error() {
+        let status = status([0x1A, 0x12, 0x1A, 0x12]).unwrap();
+        assert!(status.cutter_error);
+        assert_eq!(status.describe(), "ใบมีดตัดกระดาษขัดข้อง");
+    }
+
+    #[test]
+    fn queries_dle_eot_1_to_4() {
+        let mut printer = FakePrinter::new(&IDLE);
+        query_printer_status(&mut printer).unwrap();
+        assert_eq!(
+            printer.sent,
+            [0x10, 0x04, 1, 0x10, 0x04, 2, 0x10, 0x04, 3, 0x10, 0x04, 4]
+        );
+    }
+
+    #[test]
+    fn non_escpos_reply_is_none() {
+        // e.g. the "HTTP/1.1" of a print server's web page
+        assert!(status([b'H', b'T', b'T', b'P']).is_none());
+        assert!(status([0x12, 0x12, 0xFF, 0x12]).is_none());
+    }
+
+    #[test]
+    fn no_reply_is_none() {
+        assert!(query_printer_status(&mut FakePrinter::new(&[])).is_none());
+        assert!(query_printer_status(&mut FakePrinter::new(&[0x12, 0x12])).is_none());
+    }
+}
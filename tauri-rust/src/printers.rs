@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::database::Database;
+
+pub const PRINTER_ROLES: [&str; 4] = ["receipt", "kitchen", "bar", "label"];
+pub const PRINTER_CONNECTIONS: [&str; 2] = ["network", "cups"];
+pub const LABEL_LANGUAGES: [&str; 2] = ["tspl", "zpl"];
+
+pub const DEFAULT_PORT: i32 = 9100;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ── Request / Response types ──
+
+/// A printer set up for tickets or labels: a network printer (raw TCP,
+/// usually port 9100) or a CUPS queue. `printer.rs` only checks what the OS
+/// can see.
+///
+/// `thai_codepage` is the ESC/POS code page for TIS-620 Thai on this model;
+/// without it text is sent as UTF-8. The `label_*` fields only matter for
+/// label printers.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Printer {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub role: String,
+    pub connection: String,
+    pub host: Option<String>,
+    pub port: Option<i32>,
+    pub queue_name: Option<String>,
+    pub thai_codepage: Option<i32>,
+    pub label_language: String,
+    pub label_width_mm: i32,
+    pub label_height_mm: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryRoute {
+    pub category_id: String,
+    pub printer_id: i32,
+}
+
+// ── Helpers ──
+
+pub(crate) async fn active_printers(conn: &mut PgConnection) -> Result<Vec<Printer>, sqlx::Error> {
+    sqlx::query_as::<_, Printer>(
+        "SELECT id, name, role, connection, host, port, queue_name, thai_codepage,
+                label_language, label_width_mm, label_height_mm, is_active
+         FROM printers
+         WHERE is_active
+         ORDER BY id",
+    )
+    .fetch_all(conn)
+    .await
+}
+
+/// Printer ids per category id.
+pub(crate) async fn category_routes(
+    conn: &mut PgConnection,
+) -> Result<HashMap<String, Vec<i32>>, sqlx::Error> {
+    let routes = sqlx::query_as::<_, CategoryRoute>(
+        "SELECT category_id, printer_id FROM category_printers ORDER BY printer_id",
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let mut by_category: HashMap<String, Vec<i32>> = HashMap::new();
+    for route in routes {
+        by_category
+            .entry(route.category_id)
+            .or_default()
+            .push(route.printer_id);
+    }
+
+    Ok(by_category)
+}
+
+/// Encodes text for the printer: TIS-620 when a Thai code page is set (other
+/// non-ASCII characters become `?`), otherwise UTF-8.
+pub(crate) fn encode_text(printer: &Printer, text: &str) -> Vec<u8> {
+    if printer.thai_codepage.is_none() {
+        return text.as_bytes().to_vec();
+    }
+
+    text.chars()
+        .map(|c| match c as u32 {
+            0..=0x7F => c as u8,
+            cp @ 0x0E01..=0x0E5B => (cp - 0x0E00 + 0xA0) as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Sends raw bytes (ESC/POS, TSPL, ZPL …) to a printer.
+pub(crate) async fn send(printer: &Printer, data: &[u8]) -> Result<(), String> {
+    match printer.connection.as_str() {
+        "cups" => {
+            let queue = printer.queue_name.as_deref().unwrap_or_default();
+            let mut child = tokio::process::Command::new("lp")
+                .args(["-d", queue, "-o", "raw"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("เรียกใช้ lp ไม่ได้: {}", e))?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin
+                    .write_all(data)
+                    .await
+                    .map_err(|e| format!("ส่งงานพิมพ์ไปยัง {} ไม่สำเร็จ: {}", queue, e))?;
+            }
+
+            let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+            if !output.status.success() {
+                return Err(format!(
+                    "พิมพ์ไปยัง {} ไม่สำเร็จ: {}",
+                    queue,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Ok(())
+        }
+        _ => send_tcp(&network_address(printer).unwrap_or_default(), data).await,
+    }
+}
+
+/// `host:port` of a network printer; `None` for CUPS queues.
+pub(crate) fn network_address(printer: &Printer) -> Option<String> {
+    (printer.connection == "network").then(|| {
+        format!(
+            "{}:{}",
+            printer.host.as_deref().unwrap_or_default(),
+            printer.port.unwrap_or(DEFAULT_PORT)
+        )
+    })
+}
+
+/// Sends raw bytes to `host:port`, the way network printers take jobs.
+pub(crate) async fn send_tcp(address: &str, data: &[u8]) -> Result<(), String> {
+    let mut stream = timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(|_| format!("เชื่อมต่อเครื่องพิมพ์ {} ไม่ทันเวลา", address))?
+        .map_err(|e| format!("เชื่อมต่อเครื่องพิมพ์ {} ไม่ได้: {}", address, e))?;
+
+    timeout(WRITE_TIMEOUT, async {
+        stream.write_all(data).await?;
+        stream.shutdown().await
+    })
+    .await
+    .map_err(|_| format!("ส่งข้อมูลไปยัง {} ไม่ทันเวลา", address))?
+    .map_err(|e| format!("ส่งข้อมูลไปยัง {} ไม่สำเร็จ: {}", address, e))
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_printers(db: State<'_, Database>) -> Result<Vec<Printer>, String> {
+    sqlx::query_as::<_, Printer>(
+        "SELECT id, name, role, connection, host, port, queue_name, thai_codepage,
+                label_language, label_width_mm, label_height_mm, is_active
+         FROM printers
+         ORDER BY is_active DESC, id",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Creates a printer when `id` is 0, otherwise updates it.
+#[tauri::command]
+pub async fn save_printer(printer: Printer, db: State<'_, Database>) -> Result<i32, String> {
+    if printer.name.trim().is_empty() {
+        return Err("กรุณาระบุชื่อเครื่องพิมพ์".to_string());
+    }
+    if !PRINTER_ROLES.contains(&printer.role.as_str()) {
+        return Err(format!("หน้าที่ต้องเป็น {}", PRINTER_ROLES.join(", ")));
+    }
+    if !PRINTER_CONNECTIONS.contains(&printer.connection.as_str()) {
+        return Err(format!(
+            "การเชื่อมต่อต้องเป็น {}",
+            PRINTER_CONNECTIONS.join(", ")
+        ));
+    }
+    let blank = |value: &Option<String>| value.as_deref().unwrap_or_default().trim().is_empty();
+    if printer.connection == "network" && blank(&printer.host) {
+        return Err("กรุณาระบุ IP ของเครื่องพิมพ์".to_string());
+    }
+    if printer.connection == "cups" && blank(&printer.queue_name) {
+        return Err("กรุณาระบุชื่อคิว CUPS".to_string());
+    }
+    if !LABEL_LANGUAGES.contains(&printer.label_language.as_str()) {
+        return Err(format!(
+            "ภาษาเครื่องพิมพ์สติกเกอร์ต้องเป็น {}",
+            LABEL_LANGUAGES.join(", ")
+        ));
+    }
+    if printer.label_width_mm <= 0 || printer.label_height_mm <= 0 {
+        return Err("ขนาดสติกเกอร์ต้องมากกว่า 0".to_string());
+    }
+
+    let query = if printer.id == 0 {
+        "INSERT INTO printers (name, role, connection, host, port, queue_name, thai_codepage,
+                               label_language, label_width_mm, label_height_mm, is_active)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+         RETURNING id"
+    } else {
+        "UPDATE printers
+         SET name = $1, role = $2, connection = $3, host = $4, port = $5, queue_name = $6,
+             thai_codepage = $7, label_language = $8, label_width_mm = $9,
+             label_height_mm = $10, is_active = $11
+         WHERE id = $12
+         RETURNING id"
+    };
+
+    let mut query = sqlx::query_as::<_, (i32,)>(query)
+        .bind(printer.name.trim())
+        .bind(&printer.role)
+        .bind(&printer.connection)
+        .bind(printer.host.as_deref().map(str::trim))
+        .bind(printer.port)
+        .bind(printer.queue_name.as_deref().map(str::trim))
+        .bind(printer.thai_codepage)
+        .bind(&printer.label_language)
+        .bind(printer.label_width_mm)
+        .bind(printer.label_height_mm)
+        .bind(printer.is_active);
+    if printer.id != 0 {
+        query = query.bind(printer.id);
+    }
+
+    let (id,) = query
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| format!("บันทึกเครื่องพิมพ์ไม่สำเร็จ: {}", e))?
+        .ok_or_else(|| "ไม่พบเครื่องพิมพ์".to_string())?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn get_category_routes(db: State<'_, Database>) -> Result<Vec<CategoryRoute>, String> {
+    sqlx::query_as::<_, CategoryRoute>(
+        "SELECT category_id, printer_id FROM category_printers ORDER BY category_id, printer_id",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Replaces the printers a category's items are sent to. Categories without
+/// a route go to every kitchen printer and get no labels.
+#[tauri::command]
+pub async fn set_category_routes(
+    category_id: String,
+    printer_ids: Vec<i32>,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM category_printers WHERE category_id = $1")
+        .bind(&category_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for printer_id in &printer_ids {
+        sqlx::query(
+            "INSERT INTO category_printers (category_id, printer_id)
+             VALUES ($1, $2)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(&category_id)
+        .bind(printer_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกการส่งพิมพ์ไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printer(thai_codepage: Option<i32>) -> Printer {
+        Printer {
+            id: 1,
+            name: "Bar".to_string(),
+            role: "bar".to_string(),
+            connection: "network".to_string(),
+            host: Some("192.168.1.50".to_string()),
+            port: Some(DEFAULT_PORT),
+            queue_name: None,
+            thai_codepage,
+            label_language: "tspl".to_string(),
+            label_width_mm: 40,
+            label_height_mm: 30,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn thai_maps_to_tis620() {
+        let printer = printer(Some(21));
+        assert_eq!(encode_text(&printer, "ก"), [0xA1]);
+        assert_eq!(encode_text(&printer, "๛"), [0xFB]);
+        assert_eq!(encode_text(&printer, "฿45"), [0xDF, b'4', b'5']);
+        assert_eq!(encode_text(&printer, "กาแฟ"), [0xA1, 0xD2, 0xE1, 0xBF]);
+    }
+
+    #[test]
+    fn ascii_passes_through() {
+        assert_eq!(encode_text(&printer(Some(21)), "Latte x2\n"), b"Latte x2\n");
+    }
+
+    #[test]
+    fn other_characters_become_question_marks() {
+        assert_eq!(encode_text(&printer(Some(21)), "café☕"), b"caf??");
+    }
+
+    #[test]
+    fn utf8_without_code_page() {
+        assert_eq!(encode_text(&printer(None), "กาแฟ"), "กาแฟ".as_bytes());
+    }
+}
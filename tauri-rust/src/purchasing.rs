@@ -0,0 +1,429 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tauri::State;
+
+use crate::database::Database;
+use crate::inventory;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Supplier {
+    pub id: i32,
+    pub name: String,
+    pub contact_name: String,
+    pub phone: String,
+    pub note: String,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewSupplier {
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurchaseOrderLineInput {
+    pub stock_item_id: i32,
+    pub qty: f64,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiveLineInput {
+    pub line_id: i32,
+    pub qty: f64,
+    /// Actual cost on the supplier invoice; falls back to the ordered cost.
+    pub unit_cost: Option<f64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PurchaseOrderSummary {
+    pub id: i32,
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    pub status: String,
+    pub total: f64,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PurchaseOrderDetail {
+    pub id: i32,
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    pub status: String,
+    pub note: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub lines: Vec<PurchaseOrderLine>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct PurchaseOrderLine {
+    pub id: i32,
+    pub stock_item_id: i32,
+    pub stock_item_name: String,
+    pub unit: String,
+    pub qty_ordered: f64,
+    pub qty_received: f64,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SupplierPrice {
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    pub stock_item_id: i32,
+    pub stock_item_name: String,
+    pub last_price: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ── Suppliers ──
+
+#[tauri::command]
+pub async fn get_suppliers(db: State<'_, Database>) -> Result<Vec<Supplier>, String> {
+    sqlx::query_as::<_, Supplier>(
+        "SELECT id, name, contact_name, phone, note, is_active
+         FROM suppliers
+         WHERE is_active = TRUE
+         ORDER BY name",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_supplier(
+    supplier: NewSupplier,
+    db: State<'_, Database>,
+) -> Result<Supplier, String> {
+    sqlx::query_as::<_, Supplier>(
+        "INSERT INTO suppliers (name, contact_name, phone, note)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id, name, contact_name, phone, note, is_active",
+    )
+    .bind(&supplier.name)
+    .bind(supplier.contact_name.as_deref().unwrap_or_default())
+    .bind(supplier.phone.as_deref().unwrap_or_default())
+    .bind(supplier.note.as_deref().unwrap_or_default())
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| format!("เพิ่มซัพพลายเออร์ไม่สำเร็จ: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_supplier(
+    id: i32,
+    supplier: NewSupplier,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "UPDATE suppliers SET name = $1, contact_name = $2, phone = $3, note = $4 WHERE id = $5",
+    )
+    .bind(&supplier.name)
+    .bind(supplier.contact_name.as_deref().unwrap_or_default())
+    .bind(supplier.phone.as_deref().unwrap_or_default())
+    .bind(supplier.note.as_deref().unwrap_or_default())
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("อัปเดตซัพพลายเออร์ไม่สำเร็จ: {}", e))?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
+
+// ── Purchase orders ──
+
+#[tauri::command]
+pub async fn create_purchase_order(
+    supplier_id: i32,
+    lines: Vec<PurchaseOrderLineInput>,
+    note: Option<String>,
+    created_by: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    if lines.is_empty() {
+        return Err("ใบสั่งซื้อต้องมีอย่างน้อย 1 รายการ".to_string());
+    }
+    if lines.iter().any(|l| l.qty <= 0.0 || l.unit_cost < 0.0) {
+        return Err("จำนวนหรือราคาต่อหน่วยไม่ถูกต้อง".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let (po_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO purchase_orders (supplier_id, note, created_by)
+         VALUES ($1, $2, $3)
+         RETURNING id",
+    )
+    .bind(supplier_id)
+    .bind(note.as_deref().unwrap_or_default())
+    .bind(&created_by)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("สร้างใบสั่งซื้อไม่สำเร็จ: {}", e))?;
+
+    for line in &lines {
+        sqlx::query(
+            "INSERT INTO purchase_order_lines (po_id, stock_item_id, qty_ordered, unit_cost)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(po_id)
+        .bind(line.stock_item_id)
+        .bind(line.qty)
+        .bind(line.unit_cost)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกรายการสั่งซื้อไม่สำเร็จ: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(po_id)
+}
+
+#[tauri::command]
+pub async fn get_purchase_orders(
+    status: Option<String>,
+    db: State<'_, Database>,
+) -> Result<Vec<PurchaseOrderSummary>, String> {
+    sqlx::query_as::<_, PurchaseOrderSummary>(
+        "SELECT po.id, po.supplier_id, s.name AS supplier_name, po.status,
+                COALESCE(SUM(l.qty_ordered * l.unit_cost), 0) AS total,
+                po.created_by, po.created_at
+         FROM purchase_orders po
+         JOIN suppliers s ON s.id = po.supplier_id
+         LEFT JOIN purchase_order_lines l ON l.po_id = po.id
+         WHERE $1::text IS NULL OR po.status = $1
+         GROUP BY po.id, s.name
+         ORDER BY po.created_at DESC",
+    )
+    .bind(status)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_purchase_order_detail(
+    po_id: i32,
+    db: State<'_, Database>,
+) -> Result<Option<PurchaseOrderDetail>, String> {
+    let order = sqlx::query_as::<_, PurchaseOrderDetail>(
+        "SELECT po.id, po.supplier_id, s.name AS supplier_name, po.status, po.note,
+                po.created_by, po.created_at
+         FROM purchase_orders po
+         JOIN suppliers s ON s.id = po.supplier_id
+         WHERE po.id = $1",
+    )
+    .bind(po_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match order {
+        Some(mut detail) => {
+            detail.lines = sqlx::query_as::<_, PurchaseOrderLine>(
+                "SELECT l.id, l.stock_item_id, si.name AS stock_item_name, si.unit,
+                        l.qty_ordered, l.qty_received, l.unit_cost
+                 FROM purchase_order_lines l
+                 JOIN stock_items si ON si.id = l.stock_item_id
+                 WHERE l.po_id = $1
+                 ORDER BY l.id",
+            )
+            .bind(po_id)
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            Ok(Some(detail))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Receives goods against a purchase order. Lines may be received in several
+/// deliveries; the order becomes `received` once every line is complete.
+#[tauri::command]
+pub async fn receive_purchase_order(
+    po_id: i32,
+    lines: Vec<ReceiveLineInput>,
+    received_by: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    if lines
+        .iter()
+        .any(|l| l.qty < 0.0 || l.unit_cost.is_some_and(|c| c < 0.0))
+    {
+        return Err("จำนวนหรือราคาต่อหน่วยไม่ถูกต้อง".to_string());
+    }
+    if !lines.iter().any(|l| l.qty > 0.0) {
+        return Err("ไม่มีรายการที่รับสินค้า".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let po: Option<(i32, String)> =
+        sqlx::query_as("SELECT supplier_id, status FROM purchase_orders WHERE id = $1 FOR UPDATE")
+            .bind(po_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let (supplier_id, status) = po.ok_or_else(|| "ไม่พบใบสั่งซื้อ".to_string())?;
+    if status == "received" || status == "cancelled" {
+        return Err("ใบสั่งซื้อนี้ปิดแล้ว ไม่สามารถรับสินค้าเพิ่มได้".to_string());
+    }
+
+    let reference = format!("po:{}", po_id);
+
+    for input in lines.iter().filter(|l| l.qty > 0.0) {
+        let line: Option<(i32, f64, f64, f64)> = sqlx::query_as(
+            "SELECT stock_item_id, qty_ordered, qty_received, unit_cost
+             FROM purchase_order_lines
+             WHERE id = $1 AND po_id = $2",
+        )
+        .bind(input.line_id)
+        .bind(po_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let (stock_item_id, qty_ordered, qty_received, ordered_cost) =
+            line.ok_or_else(|| format!("ไม่พบรายการสั่งซื้อ #{}", input.line_id))?;
+
+        if qty_received + input.qty > qty_ordered {
+            return Err(format!("รับสินค้าเกินจำนวนที่สั่ง (รายการ #{})", input.line_id));
+        }
+
+        let unit_cost = input.unit_cost.unwrap_or(ordered_cost);
+
+        sqlx::query(
+            "UPDATE purchase_order_lines SET qty_received = qty_received + $1 WHERE id = $2",
+        )
+        .bind(input.qty)
+        .bind(input.line_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query(
+            "INSERT INTO goods_receipts (po_line_id, qty, unit_cost, received_by)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(input.line_id)
+        .bind(input.qty)
+        .bind(unit_cost)
+        .bind(&received_by)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกการรับสินค้าไม่สำเร็จ: {}", e))?;
+
+        inventory::record_movement(
+            &mut tx,
+            stock_item_id,
+            input.qty,
+            "purchase",
+            &reference,
+            unit_cost,
+            &received_by,
+        )
+        .await
+        .map_err(|e| format!("ปรับสต็อกไม่สำเร็จ: {}", e))?;
+
+        sqlx::query("UPDATE stock_items SET cost_per_unit = $1 WHERE id = $2")
+            .bind(unit_cost)
+            .bind(stock_item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        sqlx::query(
+            "INSERT INTO supplier_prices (supplier_id, stock_item_id, last_price, updated_at)
+             VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+             ON CONFLICT (supplier_id, stock_item_id)
+             DO UPDATE SET last_price = EXCLUDED.last_price, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(supplier_id)
+        .bind(stock_item_id)
+        .bind(unit_cost)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    let (outstanding,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*)::bigint FROM purchase_order_lines
+         WHERE po_id = $1 AND qty_received < qty_ordered",
+    )
+    .bind(po_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let new_status = if outstanding == 0 {
+        "received"
+    } else {
+        "partial"
+    };
+
+    sqlx::query(
+        "UPDATE purchase_orders
+         SET status = $1,
+             received_at = CASE WHEN $1 = 'received' THEN CURRENT_TIMESTAMP ELSE received_at END
+         WHERE id = $2",
+    )
+    .bind(new_status)
+    .bind(po_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(new_status.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_purchase_order(po_id: i32, db: State<'_, Database>) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "UPDATE purchase_orders SET status = 'cancelled' WHERE id = $1 AND status = 'open'",
+    )
+    .bind(po_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("ยกเลิกใบสั่งซื้อไม่สำเร็จ: {}", e))?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
+
+#[tauri::command]
+pub async fn get_supplier_prices(
+    stock_item_id: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<Vec<SupplierPrice>, String> {
+    sqlx::query_as::<_, SupplierPrice>(
+        "SELECT sp.supplier_id, s.name AS supplier_name, sp.stock_item_id,
+                si.name AS stock_item_name, sp.last_price, sp.updated_at
+         FROM supplier_prices sp
+         JOIN suppliers s ON s.id = sp.supplier_id
+         JOIN stock_items si ON si.id = sp.stock_item_id
+         WHERE $1::int IS NULL OR sp.stock_item_id = $1
+         ORDER BY si.name, sp.last_price",
+    )
+    .bind(stock_item_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,409 @@
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::database::Database;
+use crate::menu_history::{self, MenuItemSnapshot};
+
+/// `unit` items are sold by the piece; for the others `price` is per kg or
+/// per 100 g and the order line qty is the weight in that unit.
+pub const SOLD_BY: [&str; 3] = ["unit", "kg", "100g"];
+
+const DEFAULT_BAUD_RATE: u32 = 9600;
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// Frames to read before giving up on a weight that never settles.
+const MAX_FRAMES: usize = 50;
+const MAX_FRAME_LEN: usize = 64;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize)]
+pub struct ScaleReading {
+    pub grams: f64,
+    pub stable: bool,
+    /// Weight in the unit of the item's `sold_by`, ready to use as the order
+    /// line qty. Only set when `sold_by` is given.
+    pub qty: Option<f64>,
+}
+
+// ── Helpers ──
+
+/// Order line qty for `grams` of an item sold by `sold_by`, to the gram.
+fn weighed_qty(sold_by: &str, grams: f64) -> Option<f64> {
+    let grams = grams.round();
+    match sold_by {
+        "kg" => Some(grams / 1000.0),
+        "100g" => Some(grams / 100.0),
+        _ => None,
+    }
+}
+
+/// Parses one ASCII weight frame, e.g. CAS `ST,GS,+  0.250kg` or Toledo
+/// `S S      0.250 kg`. Returns `None` for overload, underload and frames
+/// without a weight or a known unit.
+fn parse_frame(frame: &str) -> Option<ScaleReading> {
+    let frame: String = frame.chars().filter(|c| !c.is_control()).collect();
+    let upper = frame.trim().to_ascii_uppercase();
+    if upper.starts_with("OL") || upper.starts_with("S +") || upper.starts_with("S -") {
+        return None;
+    }
+    let stable = !(upper.starts_with("US") || upper.starts_with("S D") || upper.contains(",US,"));
+
+    let start = frame.find(|c: char| c.is_ascii_digit())?;
+    let end = frame[start..]
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map_or(frame.len(), |i| start + i);
+    let value: f64 = frame[start..end].parse().ok()?;
+    let sign = if frame[..start].trim_end().ends_with('-') {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let unit = frame[end..]
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let grams = match unit.as_str() {
+        "kg" => value * 1000.0,
+        "g" => value,
+        "lb" => value * 453.592_37,
+        "oz" => value * 28.349_523_125,
+        _ => return None,
+    };
+
+    Some(ScaleReading {
+        grams: sign * grams,
+        stable,
+        qty: None,
+    })
+}
+
+/// Reads frames until the scale reports a stable weight. Scales that only
+/// answer on request get `request` (e.g. `W\r` or `S\r\n`) before each frame;
+/// others stream frames continuously. Works on any `Read + Write`, so a
+/// pseudo-terminal can stand in for the scale: create a pair with
+/// `socat -d -d pty,raw,echo=0 pty,raw,echo=0`, open one end here and write
+/// frames like `ST,GS,+  0.250kg\r\n` to the other.
+pub(crate) fn read_weight<P: Read + Write + ?Sized>(
+    port: &mut P,
+    request: Option<&[u8]>,
+) -> Result<ScaleReading, String> {
+    let mut frame = Vec::new();
+    let mut byte = [0u8; 1];
+
+    for _ in 0..MAX_FRAMES {
+        if let Some(request) = request {
+            port.write_all(request)
+                .and_then(|_| port.flush())
+                .map_err(|e| format!("ส่งคำสั่งไปยังเครื่องชั่งไม่สำเร็จ: {}", e))?;
+        }
+
+        frame.clear();
+        loop {
+            match port.read(&mut byte) {
+                Ok(0) => return Err("การเชื่อมต่อเครื่องชั่งถูกปิด".to_string()),
+                Ok(_) if byte[0] == b'\r' || byte[0] == b'\n' => {
+                    if !frame.is_empty() {
+                        break;
+                    }
+                }
+                Ok(_) => {
+                    frame.push(byte[0]);
+                    if frame.len() > MAX_FRAME_LEN {
+                        frame.clear();
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::TimedOut => {
+                    return Err("เครื่องชั่งไม่ตอบสนอง".to_string())
+                }
+                Err(e) => return Err(format!("อ่านค่าจากเครื่องชั่งไม่สำเร็จ: {}", e)),
+            }
+        }
+
+        if let Some(reading) = parse_frame(&String::from_utf8_lossy(&frame)) {
+            if reading.stable {
+                return Ok(reading);
+            }
+        }
+    }
+
+    Err("น้ำหนักยังไม่นิ่ง กรุณาชั่งใหม่".to_string())
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub fn get_serial_ports() -> Result<Vec<String>, String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+        .map_err(|e| e.to_string())
+}
+
+/// Reads a stable weight from the scale on `port`. With `sold_by` given, the
+/// reading also carries the order line qty for that item.
+#[tauri::command]
+pub async fn read_scale_weight(
+    port: String,
+    baud_rate: Option<u32>,
+    request: Option<String>,
+    sold_by: Option<String>,
+) -> Result<ScaleReading, String> {
+    let mut reading = tokio::task::spawn_blocking(move || {
+        let mut serial = serialport::new(&port, baud_rate.unwrap_or(DEFAULT_BAUD_RATE))
+            .timeout(READ_TIMEOUT)
+            .open()
+            .map_err(|e| format!("เปิดพอร์ตเครื่องชั่ง {} ไม่ได้: {}", port, e))?;
+
+        read_weight(
+            &mut *serial,
+            request
+                .as_deref()
+                .filter(|r| !r.is_empty())
+                .map(str::as_bytes),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if reading.grams <= 0.0 {
+        return Err("ไม่มีสินค้าบนเครื่องชั่ง".to_string());
+    }
+    reading.qty = sold_by
+        .as_deref()
+        .and_then(|s| weighed_qty(s, reading.grams));
+
+    Ok(reading)
+}
+
+#[tauri::command]
+pub async fn set_menu_item_sold_by(
+    id: i32,
+    sold_by: String,
+    changed_by: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if !SOLD_BY.contains(&sold_by.as_str()) {
+        return Err(format!("หน่วยขายต้องเป็น {}", SOLD_BY.join(", ")));
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(before) = menu_history::snapshot(&mut tx, id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query("UPDATE menu_items SET sold_by = $1 WHERE id = $2")
+        .bind(&sold_by)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("บันทึกหน่วยขายไม่สำเร็จ: {}", e))?;
+
+    let after = MenuItemSnapshot {
+        sold_by,
+        ..before.clone()
+    };
+    menu_history::record_changes(&mut tx, id, &before, &after, &changed_by)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::*;
+
+    /// Stands in for the serial port: reads come from `input`, writes are
+    /// collected in `sent`.
+    struct FakeScale {
+        input: Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl FakeScale {
+        fn new(frames: &str) -> Self {
+            FakeScale {
+                input: Cursor::new(frames.as_bytes().to_vec()),
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for FakeScale {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeScale {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn grams(frames: &str) -> Result<f64, String> {
+        read_weight(&mut FakeScale::new(frames), None).map(|r| r.grams)
+    }
+
+    #[test]
+    fn cas_stable_frame() {
+        assert_eq!(grams("ST,GS,+  0.250kg\r\n"), Ok(250.0));
+    }
+
+    #[test]
+    fn toledo_stable_frame() {
+        assert_eq!(grams("S S      0.250 kg\r\n"), Ok(250.0));
+        assert_eq!(grams("S S    350 g\r\n"), Ok(350.0));
+    }
+
+    #[test]
+    fn waits_for_a_stable_weight() {
+        assert_eq!(
+            grams("US,GS,+  0.180kg\r\nUS,GS,+  0.240kg\r\nST,GS,+  0.250kg\r\n"),
+            Ok(250.0)
+        );
+        assert_eq!(
+            grams("S D      0.180 kg\r\nS S      0.250 kg\r\n"),
+            Ok(250.0)
+        );
+    }
+
+    #[test]
+    fn never_stable() {
+        let frames = "US,GS,+  0.180kg\r\n".repeat(MAX_FRAMES + 1);
+        assert_eq!(grams(&frames), Err("น้ำหนักยังไม่นิ่ง กรุณาชั่งใหม่".to_string()));
+    }
+
+    #[test]
+    fn overload_and_underload_are_skipped() {
+        assert!(parse_frame("OL,GS,+  9.999kg").is_none());
+        assert!(parse_frame("S +").is_none());
+        assert!(parse_frame("S -").is_none());
+        assert_eq!(
+            grams("OL,GS,+  9.999kg\r\nS +\r\nST,GS,+  1.000kg\r\n"),
+            Ok(1000.0)
+        );
+    }
+
+    #[test]
+    fn negative_and_tare_weights() {
+        assert_eq!(grams("ST,NT,-  0.050kg\r\n"), Ok(-50.0));
+        assert_eq!(grams("S S     -0.050 kg\r\n"), Ok(-50.0));
+        assert_eq!(grams("ST,NT,+  0.000kg\r\n"), Ok(0.0));
+    }
+
+    #[test]
+    fn garbage_is_skipped() {
+        assert!(parse_frame("hello").is_none());
+        assert!(parse_frame("ST,GS,+  0.250xx").is_none());
+        assert!(parse_frame("").is_none());
+        assert_eq!(
+            grams("\x02\x7f??\r\nST,GS,+  0.250\r\nST,GS,+  0.250kg\r\n"),
+            Ok(250.0)
+        );
+        assert_eq!(grams("hello\r\n"), Err("การเชื่อมต่อเครื่องชั่งถูกปิด".to_string()));
+    }
+
+    #[test]
+    fn sends_the_request_before_each_frame() {
+        let mut scale = FakeScale::new("S D      0.180 kg\r\nS S      0.250 kg\r\n");
+        let reading = read_weight(&mut scale, Some(b"S\r\n")).unwrap();
+        assert!(reading.stable);
+        assert_eq!(scale.sent, b"S\r\nS\r\n");
+    }
+
+    #[test]
+    fn pounds_and_ounces() {
+        let lb = parse_frame("ST,GS,+  1.00lb").unwrap().grams;
+        assert!((lb - 453.592).abs() < 0.001);
+        let oz = parse_frame("S S      2.00 oz").unwrap().grams;
+        assert!((oz - 56.699).abs() < 0.001);
+    }
+
+    #[test]
+    fn weighed_qty_rounds_to_the_gram() {
+        assert_eq!(weighed_qty("kg", 250.4), Some(0.25));
+        assert_eq!(weighed_qty("100g", 350.0), Some(3.5));
+        assert_eq!(weighed_qty("unit", 350.0), None);
+    }
+
+    /// The real serial path: frames written to one end of a pseudo-terminal
+    /// are read by `read_weight` on the other.
+    #[cfg(unix)]
+    mod pty {
+        use std::io::{BufRead, BufReader, Write};
+        use std::thread;
+
+        use serialport::{SerialPort, TTYPort};
+
+        use super::super::*;
+
+        fn pair() -> (TTYPort, TTYPort) {
+            let (scale, mut port) = TTYPort::pair().expect("pseudo-terminal");
+            port.set_timeout(READ_TIMEOUT).unwrap();
+            (scale, port)
+        }
+
+        #[test]
+        fn streaming_cas_scale() {
+            let (mut scale, mut port) = pair();
+            scale
+                .write_all(b"US,GS,+  0.180kg\r\nOL,GS,+  9.999kg\r\nST,GS,+  0.250kg\r\n")
+                .unwrap();
+
+            let reading = read_weight(&mut port, None).unwrap();
+            assert_eq!(reading.grams, 250.0);
+            assert!(reading.stable);
+        }
+
+        #[test]
+        fn toledo_scale_answers_on_request() {
+            let (scale, mut port) = pair();
+            let answers = thread::spawn(move || {
+                let mut reader = BufReader::new(scale.try_clone_native().unwrap());
+                let mut scale = scale;
+                for frame in ["S D      0.180 kg", "S S     -0.050 kg"] {
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    assert_eq!(request, "S\r\n");
+                    scale
+                        .write_all(format!("{}\r\n", frame).as_bytes())
+                        .unwrap();
+                }
+                // Closing the scale's end before the port is done with it
+                // would fail the port's last flush.
+                scale
+            });
+
+            let reading = read_weight(&mut port, Some(b"S\r\n")).unwrap();
+            let _scale = answers.join().unwrap();
+            assert_eq!(reading.grams, -50.0);
+        }
+
+        #[test]
+        fn silent_scale_times_out() {
+            let (_scale, mut port) = pair();
+            assert_eq!(
+                read_weight(&mut port, None).map(|r| r.grams),
+                Err("เครื่องชั่งไม่ตอบสนอง".to_string())
+            );
+        }
+    }
+}
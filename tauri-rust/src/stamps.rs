@@ -0,0 +1,286 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::menu;
+use crate::orders::OrderItemInput;
+
+// ── Request / Response types ──
+
+/// One stamp is earned per unit of an item in `qualifying_categories`; every
+/// `stamps_required` stamps unlock one free qualifying item.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct StampConfig {
+    pub qualifying_categories: Vec<String>,
+    pub stamps_required: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StampEntry {
+    pub id: i32,
+    pub order_id: Option<String>,
+    pub stamps: i32,
+    pub reason: String,
+    pub note: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StampCard {
+    pub customer_id: i32,
+    pub stamps: i64,
+    pub stamps_required: i32,
+    pub rewards_available: i64,
+    pub history: Vec<StampEntry>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StampOutcome {
+    pub earned: i32,
+    pub rewards_redeemed: i32,
+}
+
+// ── Helpers ──
+
+async fn stamp_config(conn: &mut PgConnection) -> Result<StampConfig, sqlx::Error> {
+    sqlx::query_as::<_, StampConfig>(
+        "SELECT qualifying_categories, stamps_required, is_active FROM stamp_config WHERE id = 1",
+    )
+    .fetch_one(conn)
+    .await
+}
+
+async fn stamp_balance(conn: &mut PgConnection, customer_id: i32) -> Result<i64, sqlx::Error> {
+    let (balance,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(stamps), 0)::bigint FROM stamp_ledger WHERE customer_id = $1",
+    )
+    .bind(customer_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(balance)
+}
+
+async fn add_stamps(
+    conn: &mut PgConnection,
+    customer_id: i32,
+    order_id: Option<&str>,
+    stamps: i32,
+    reason: &str,
+    note: &str,
+    created_by: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO stamp_ledger (customer_id, order_id, stamps, reason, note, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(customer_id)
+    .bind(order_id)
+    .bind(stamps)
+    .bind(reason)
+    .bind(note)
+    .bind(created_by)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Earns stamps for the qualifying items of an order and redeems one reward
+/// per unit of every `is_reward` line. Rewards must be covered by the stamps
+/// the customer had before this order.
+pub(crate) async fn apply_stamps(
+    conn: &mut PgConnection,
+    customer_id: Option<i32>,
+    order_id: &str,
+    items: &[OrderItemInput],
+    created_by: &str,
+) -> Result<StampOutcome, String> {
+    let has_reward = items.iter().any(|i| i.is_reward);
+
+    let config = stamp_config(&mut *conn).await.map_err(|e| e.to_string())?;
+    if !config.is_active {
+        if has_reward {
+            return Err("ยังไม่ได้เปิดใช้บัตรสะสมแสตมป์".to_string());
+        }
+        return Ok(StampOutcome::default());
+    }
+
+    let customer_id = match customer_id {
+        Some(id) => id,
+        None if has_reward => return Err("ต้องระบุลูกค้าก่อนใช้สิทธิ์แลกแสตมป์".to_string()),
+        None => return Ok(StampOutcome::default()),
+    };
+
+    let item_ids: Vec<i32> = items.iter().map(|i| i.item_id).collect();
+    let categories = menu::item_categories(&mut *conn, &item_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let qualifies = |item: &OrderItemInput| {
+        categories
+            .get(&item.item_id)
+            .is_some_and(|c| config.qualifying_categories.contains(c))
+    };
+
+    let mut outcome = StampOutcome::default();
+    let mut rewarded = Vec::new();
+    for item in items {
+        if item.is_reward {
+            if !qualifies(item) {
+                return Err(format!("{} ไม่อยู่ในรายการที่แลกแสตมป์ได้", item.item_name));
+            }
+            outcome.rewards_redeemed += item.qty as i32;
+            rewarded.push(format!("{} x{}", item.item_name, item.qty));
+        } else if qualifies(item) {
+            // A weighed line earns one stamp whatever its weight.
+            outcome.earned += if item.is_weighed() {
+                1
+            } else {
+                item.qty as i32
+            };
+        }
+    }
+
+    sqlx::query("SELECT id FROM customers WHERE id = $1 FOR UPDATE")
+        .bind(customer_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if outcome.rewards_redeemed > 0 {
+        let needed = outcome.rewards_redeemed * config.stamps_required;
+        let balance = stamp_balance(&mut *conn, customer_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        if i64::from(needed) > balance {
+            return Err(format!(
+                "แสตมป์ไม่พอ (มี {} ดวง ต้องใช้ {} ดวง)",
+                balance, needed
+            ));
+        }
+
+        add_stamps(
+            &mut *conn,
+            customer_id,
+            Some(order_id),
+            -needed,
+            "redeem",
+            &rewarded.join(", "),
+            created_by,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    if outcome.earned > 0 {
+        add_stamps(
+            &mut *conn,
+            customer_id,
+            Some(order_id),
+            outcome.earned,
+            "earn",
+            "",
+            created_by,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(outcome)
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_stamp_config(db: State<'_, Database>) -> Result<StampConfig, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    stamp_config(&mut conn).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_stamp_config(
+    config: StampConfig,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if config.stamps_required <= 0 {
+        return Err("จำนวนแสตมป์ที่ต้องสะสมต้องมากกว่า 0".to_string());
+    }
+
+    sqlx::query(
+        "UPDATE stamp_config
+         SET qualifying_categories = $1, stamps_required = $2, is_active = $3
+         WHERE id = 1",
+    )
+    .bind(&config.qualifying_categories)
+    .bind(config.stamps_required)
+    .bind(config.is_active)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกการตั้งค่าบัตรสะสมแสตมป์ไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn get_stamp_card(
+    customer_id: i32,
+    db: State<'_, Database>,
+) -> Result<StampCard, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    let config = stamp_config(&mut conn).await.map_err(|e| e.to_string())?;
+    let stamps = stamp_balance(&mut conn, customer_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let history = sqlx::query_as::<_, StampEntry>(
+        "SELECT id, order_id, stamps, reason, note, created_by, created_at
+         FROM stamp_ledger
+         WHERE customer_id = $1
+         ORDER BY created_at DESC, id DESC",
+    )
+    .bind(customer_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(StampCard {
+        customer_id,
+        stamps,
+        stamps_required: config.stamps_required,
+        rewards_available: stamps / i64::from(config.stamps_required.max(1)),
+        history,
+    })
+}
+
+/// Manual correction, e.g. when moving a customer's paper card over.
+#[tauri::command]
+pub async fn adjust_stamps(
+    customer_id: i32,
+    stamps: i32,
+    note: Option<String>,
+    user_id: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    add_stamps(
+        &mut conn,
+        customer_id,
+        None,
+        stamps,
+        "adjust",
+        note.as_deref().unwrap_or_default(),
+        &user_id,
+    )
+    .await
+    .map_err(|e| format!("ปรับแสตมป์ไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use tauri::State;
+
+use crate::database::Database;
+use crate::inventory;
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Stocktake {
+    pub id: i32,
+    pub status: String,
+    pub note: String,
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+    pub closed_by: Option<String>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// One stock item in a stocktake. While the session is open `system_qty` is
+/// the live stock level; after closing it is the level at close time.
+#[derive(Debug, Serialize, FromRow)]
+pub struct StocktakeLine {
+    pub stock_item_id: i32,
+    pub stock_item_name: String,
+    pub unit: String,
+    pub system_qty: f64,
+    pub counted_qty: f64,
+    pub variance: f64,
+    pub unit_cost: f64,
+    pub variance_value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StocktakeReport {
+    pub stocktake: Stocktake,
+    pub lines: Vec<StocktakeLine>,
+    pub total_variance_value: f64,
+}
+
+// ── Helpers ──
+
+async fn stocktake_lines(
+    pool: &sqlx::PgPool,
+    stocktake_id: i32,
+    closed: bool,
+) -> Result<Vec<StocktakeLine>, sqlx::Error> {
+    if closed {
+        sqlx::query_as::<_, StocktakeLine>(
+            "SELECT r.stock_item_id, si.name AS stock_item_name, si.unit, r.system_qty,
+                    r.counted_qty, (r.counted_qty - r.system_qty) AS variance, r.unit_cost,
+                    ((r.counted_qty - r.system_qty) * r.unit_cost) AS variance_value
+             FROM stocktake_results r
+             JOIN stock_items si ON si.id = r.stock_item_id
+             WHERE r.stocktake_id = $1
+             ORDER BY si.name",
+        )
+        .bind(stocktake_id)
+        .fetch_all(pool)
+        .await
+    } else {
+        sqlx::query_as::<_, StocktakeLine>(
+            "SELECT c.stock_item_id, si.name AS stock_item_name, si.unit, si.qty AS system_qty,
+                    SUM(c.counted_qty) AS counted_qty, (SUM(c.counted_qty) - si.qty) AS variance,
+                    si.cost_per_unit AS unit_cost,
+                    ((SUM(c.counted_qty) - si.qty) * si.cost_per_unit) AS variance_value
+             FROM stocktake_counts c
+             JOIN stock_items si ON si.id = c.stock_item_id
+             WHERE c.stocktake_id = $1
+             GROUP BY c.stock_item_id, si.id
+             ORDER BY si.name",
+        )
+        .bind(stocktake_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+// ── Commands ──
+
+/// Opens a new stocktake session. Only one session may be open at a time.
+#[tauri::command]
+pub async fn open_stocktake(
+    note: Option<String>,
+    user_id: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO stocktakes (note, opened_by)
+         VALUES ($1, $2)
+         RETURNING id",
+    )
+    .bind(note.as_deref().unwrap_or_default())
+    .bind(&user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+        Some(code) if code == "23505" => "มีการตรวจนับสต็อกที่ยังไม่ปิดอยู่แล้ว".to_string(),
+        _ => format!("เปิดการตรวจนับไม่สำเร็จ: {}", e),
+    })?;
+
+    Ok(id)
+}
+
+/// Records a counted quantity. Counts are kept per location so several
+/// devices can count the same item in different areas; entering a count for
+/// the same item and location again replaces the previous one.
+#[tauri::command]
+pub async fn record_stocktake_count(
+    stocktake_id: i32,
+    stock_item_id: i32,
+    location: Option<String>,
+    counted_qty: f64,
+    user_id: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if counted_qty < 0.0 {
+        return Err("จำนวนที่นับต้องไม่ติดลบ".to_string());
+    }
+
+    let rows = sqlx::query(
+        "INSERT INTO stocktake_counts (stocktake_id, stock_item_id, location, counted_qty, counted_by)
+         SELECT $1, $2, $3, $4, $5
+         WHERE EXISTS (SELECT 1 FROM stocktakes WHERE id = $1 AND status = 'open')
+         ON CONFLICT (stocktake_id, stock_item_id, location)
+         DO UPDATE SET counted_qty = EXCLUDED.counted_qty,
+                       counted_by = EXCLUDED.counted_by,
+                       counted_at = CURRENT_TIMESTAMP",
+    )
+    .bind(stocktake_id)
+    .bind(stock_item_id)
+    .bind(location.as_deref().unwrap_or_default())
+    .bind(counted_qty)
+    .bind(&user_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกจำนวนนับไม่สำเร็จ: {}", e))?
+    .rows_affected();
+
+    Ok(rows > 0)
+}
+
+#[tauri::command]
+pub async fn get_stocktakes(db: State<'_, Database>) -> Result<Vec<Stocktake>, String> {
+    sqlx::query_as::<_, Stocktake>(
+        "SELECT id, status, note, opened_by, opened_at, closed_by, closed_at
+         FROM stocktakes
+         ORDER BY opened_at DESC",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_stocktake_report(
+    stocktake_id: i32,
+    db: State<'_, Database>,
+) -> Result<Option<StocktakeReport>, String> {
+    let stocktake = sqlx::query_as::<_, Stocktake>(
+        "SELECT id, status, note, opened_by, opened_at, closed_by, closed_at
+         FROM stocktakes WHERE id = $1",
+    )
+    .bind(stocktake_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match stocktake {
+        Some(stocktake) => {
+            let lines = stocktake_lines(&db.pool, stocktake_id, stocktake.status == "closed")
+                .await
+                .map_err(|e| e.to_string())?;
+
+            Ok(Some(StocktakeReport {
+                total_variance_value: lines.iter().map(|l| l.variance_value).sum(),
+                stocktake,
+                lines,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Closes a stocktake: snapshots system stock against the counts and posts an
+/// adjustment movement for every item with a variance. Items that were never
+/// counted are left untouched.
+#[tauri::command]
+pub async fn close_stocktake(
+    stocktake_id: i32,
+    user_id: String,
+    db: State<'_, Database>,
+) -> Result<Option<StocktakeReport>, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let status: Option<(String,)> =
+        sqlx::query_as("SELECT status FROM stocktakes WHERE id = $1 FOR UPDATE")
+            .bind(stocktake_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    match status {
+        Some((s,)) if s == "open" => {}
+        Some(_) => return Err("การตรวจนับนี้ปิดไปแล้ว".to_string()),
+        None => return Ok(None),
+    }
+
+    // Lock the counted items so no other movement lands between the snapshot
+    // and the adjustment.
+    sqlx::query(
+        "SELECT id FROM stock_items
+         WHERE id IN (SELECT stock_item_id FROM stocktake_counts WHERE stocktake_id = $1)
+         FOR UPDATE",
+    )
+    .bind(stocktake_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let counts: Vec<(i32, f64, f64, f64)> = sqlx::query_as(
+        "SELECT si.id, si.qty, SUM(c.counted_qty), si.cost_per_unit
+         FROM stocktake_counts c
+         JOIN stock_items si ON si.id = c.stock_item_id
+         WHERE c.stocktake_id = $1
+         GROUP BY si.id",
+    )
+    .bind(stocktake_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let reference = format!("stocktake:{}", stocktake_id);
+
+    for (stock_item_id, system_qty, counted_qty, unit_cost) in counts {
+        sqlx::query(
+            "INSERT INTO stocktake_results (stocktake_id, stock_item_id, system_qty, counted_qty, unit_cost)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(stocktake_id)
+        .bind(stock_item_id)
+        .bind(system_qty)
+        .bind(counted_qty)
+        .bind(unit_cost)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let variance = counted_qty - system_qty;
+        if variance != 0.0 {
+            inventory::record_movement(
+                &mut tx,
+                stock_item_id,
+                variance,
+                "adjustment",
+                &reference,
+                unit_cost,
+                &user_id,
+            )
+            .await
+            .map_err(|e| format!("ปรับสต็อกไม่สำเร็จ: {}", e))?;
+        }
+    }
+
+    sqlx::query(
+        "UPDATE stocktakes
+         SET status = 'closed', closed_by = $1, closed_at = CURRENT_TIMESTAMP
+         WHERE id = $2",
+    )
+    .bind(&user_id)
+    .bind(stocktake_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    get_stocktake_report(stocktake_id, db).await
+}
+
+#[tauri::command]
+pub async fn cancel_stocktake(stocktake_id: i32, db: State<'_, Database>) -> Result<bool, String> {
+    let rows =
+        sqlx::query("UPDATE stocktakes SET status = 'cancelled' WHERE id = $1 AND status = 'open'")
+            .bind(stocktake_id)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .rows_affected();
+
+    Ok(rows > 0)
+}
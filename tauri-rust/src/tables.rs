@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::combos::{self, ComboOrderInput};
+use crate::database::Database;
+use crate::kitchen;
+use crate::labels;
+use crate::orders::{self, OrderItemInput};
+use crate::pricing;
+use crate::tickets;
+
+pub const ORDER_TYPES: [&str; 3] = ["dine_in", "takeaway", "delivery"];
+
+// ── Request / Response types ──
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DiningZone {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub sort_order: i32,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tables: Vec<DiningTable>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DiningTable {
+    #[serde(default)]
+    pub id: i32,
+    pub zone_id: i32,
+    pub name: String,
+    pub seats: i32,
+    pub sort_order: i32,
+    pub is_active: bool,
+    /// Tabs currently open on the table; filled by `get_table_layout`.
+    #[serde(default)]
+    pub open_tabs: i64,
+}
+
+/// An open bill on a table that collects items until it is settled through
+/// `create_order`. Item prices are fixed when the items are added.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Tab {
+    pub id: i32,
+    pub table_id: i32,
+    pub table_name: String,
+    pub guests: i32,
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+    /// Set once the bill is being split evenly; `shares_paid` of them are paid.
+    pub split_ways: Option<i32>,
+    pub shares_paid: i32,
+    pub total: f64,
+    #[sqlx(skip)]
+    pub items: Vec<TabItem>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TabItem {
+    pub id: i32,
+    pub item_id: i32,
+    pub item_name: String,
+    pub qty: f64,
+    pub price: f64,
+    pub sold_by: String,
+    pub combo_id: Option<i32>,
+    pub combo_index: Option<i32>,
+    /// A free stamp reward; the stamps are redeemed when the tab is settled.
+    pub is_reward: bool,
+    pub added_by: String,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct TabItemRow {
+    tab_id: i32,
+    #[sqlx(flatten)]
+    item: TabItem,
+}
+
+/// A round of items sent to the kitchen by `add_tab_items`.
+#[derive(Debug, Serialize)]
+pub struct TabRound {
+    pub round_id: i32,
+    /// From the daily order number sequence; printed on the tickets.
+    pub order_number: i32,
+    /// Kitchen/bar tickets and cup stickers queued for the round.
+    pub print_jobs: Vec<i32>,
+}
+
+/// Moves `qty` of a tab line to another tab; the line is split when `qty` is
+/// less than the line's qty.
+#[derive(Debug, Deserialize)]
+pub struct TabSplitLine {
+    pub tab_item_id: i32,
+    pub qty: f64,
+}
+
+/// What `create_order` charges when settling a tab.
+pub(crate) struct TabCharge {
+    pub table_id: i32,
+    /// The tab's lines, on the settlement that closes the tab only.
+    pub items: Vec<OrderItemInput>,
+    /// This settlement's share of the tab total, before discounts.
+    pub amount: f64,
+    /// How many shares the tab is split into; 1 when paid in one go.
+    pub ways: i32,
+}
+
+// ── Helpers ──
+
+/// Locks an open tab and returns its `shares_paid`.
+async fn lock_open_tab(conn: &mut PgConnection, tab_id: i32) -> Result<i32, String> {
+    let tab: Option<(String, i32)> =
+        sqlx::query_as("SELECT status, shares_paid FROM tabs WHERE id = $1 FOR UPDATE")
+            .bind(tab_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    match tab {
+        None => Err("ไม่พบบิลโต๊ะ".to_string()),
+        Some((status, _)) if status != "open" => Err("บิลโต๊ะนี้ปิดไปแล้ว".to_string()),
+        Some((_, shares_paid)) => Ok(shares_paid),
+    }
+}
+
+/// Locks an open tab that has no split payments yet, so its lines can change.
+async fn lock_unpaid_tab(conn: &mut PgConnection, tab_id: i32) -> Result<(), String> {
+    if lock_open_tab(conn, tab_id).await? > 0 {
+        return Err("บิลนี้เริ่มแบ่งจ่ายแล้ว แก้ไขรายการไม่ได้".to_string());
+    }
+
+    Ok(())
+}
+
+/// First free `combo_index` on a tab, so combos added in different rounds
+/// stay apart.
+async fn next_combo_index(conn: &mut PgConnection, tab_id: i32) -> Result<i32, sqlx::Error> {
+    let (next,): (i32,) =
+        sqlx::query_as("SELECT COALESCE(MAX(combo_index) + 1, 0) FROM tab_items WHERE tab_id = $1")
+            .bind(tab_id)
+            .fetch_one(conn)
+            .await?;
+
+    Ok(next)
+}
+
+async fn load_tabs(
+    conn: &mut PgConnection,
+    table_id: Option<i32>,
+) -> Result<Vec<Tab>, sqlx::Error> {
+    let mut tabs = sqlx::query_as::<_, Tab>(
+        "SELECT t.id, t.table_id, dt.name AS table_name, t.guests, t.opened_by, t.opened_at,
+                t.split_ways, t.shares_paid,
+                COALESCE((SELECT SUM(i.qty * i.price) FROM tab_items i WHERE i.tab_id = t.id), 0)
+                    AS total
+         FROM tabs t
+         JOIN dining_tables dt ON dt.id = t.table_id
+         WHERE t.status = 'open' AND ($1::integer IS NULL OR t.table_id = $1)
+         ORDER BY t.opened_at",
+    )
+    .bind(table_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let tab_ids: Vec<i32> = tabs.iter().map(|t| t.id).collect();
+    let items = sqlx::query_as::<_, TabItemRow>(
+        "SELECT tab_id, id, item_id, item_name, qty, price, sold_by, combo_id, combo_index,
+                is_reward, added_by, added_at
+         FROM tab_items
+         WHERE tab_id = ANY($1)
+         ORDER BY id",
+    )
+    .bind(&tab_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut by_tab: HashMap<i32, Vec<TabItem>> = HashMap::new();
+    for row in items {
+        by_tab.entry(row.tab_id).or_default().push(row.item);
+    }
+    for tab in &mut tabs {
+        tab.items = by_tab.remove(&tab.id).unwrap_or_default();
+    }
+
+    Ok(tabs)
+}
+
+/// What to charge for settling a tab. With `split_ways`, each settlement
+/// charges an equal share of the total and the tab stays open until all
+/// shares are paid. Lines are never divided: the earlier shares are charged
+/// as an amount only, and the last one carries every line and the rounding
+/// remainder.
+pub(crate) async fn charge_tab(
+    conn: &mut PgConnection,
+    tab_id: i32,
+    split_ways: Option<i32>,
+) -> Result<TabCharge, String> {
+    let shares_paid = lock_open_tab(&mut *conn, tab_id).await?;
+
+    let (table_id, current_ways): (i32, Option<i32>) =
+        sqlx::query_as("SELECT table_id, split_ways FROM tabs WHERE id = $1")
+            .bind(tab_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let ways = match (current_ways, split_ways) {
+        (Some(current), Some(ways)) if current != ways => {
+            return Err(format!("บิลนี้แบ่งจ่าย {} คนไปแล้ว", current))
+        }
+        (Some(current), _) => current,
+        (None, Some(ways)) if ways < 2 => return Err("ต้องแบ่งจ่ายอย่างน้อย 2 คน".to_string()),
+        (None, Some(ways)) => {
+            sqlx::query("UPDATE tabs SET split_ways = $1 WHERE id = $2")
+                .bind(ways)
+                .bind(tab_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            ways
+        }
+        (None, None) => 1,
+    };
+
+    let lines = sqlx::query_as::<_, TabItem>(
+        "SELECT id, item_id, item_name, qty, price, sold_by, combo_id, combo_index,
+                is_reward, added_by, added_at
+         FROM tab_items
+         WHERE tab_id = $1
+         ORDER BY id",
+    )
+    .bind(tab_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if lines.is_empty() {
+        return Err("ไม่มีรายการในบิลโต๊ะ".to_string());
+    }
+
+    let items: Vec<OrderItemInput> = lines
+        .into_iter()
+        .map(|line| OrderItemInput {
+            item_id: line.item_id,
+            item_name: line.item_name,
+            qty: line.qty,
+            price: line.price,
+            is_reward: line.is_reward,
+            combo_id: line.combo_id,
+            combo_index: line.combo_index,
+            sold_by: line.sold_by,
+        })
+        .collect();
+
+    let total = pricing::order_subtotal(&items);
+    let share = (total / f64::from(ways) * 100.0).round() / 100.0;
+    if shares_paid + 1 < ways {
+        return Ok(TabCharge {
+            table_id,
+            items: Vec::new(),
+            amount: share,
+            ways,
+        });
+    }
+
+    Ok(TabCharge {
+        table_id,
+        items,
+        amount: ((total - share * f64::from(ways - 1)) * 100.0).round() / 100.0,
+        ways,
+    })
+}
+
+/// Counts one paid settlement, and closes the tab once every share is paid.
+pub(crate) async fn record_tab_payment(
+    conn: &mut PgConnection,
+    tab_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE tabs
+         SET shares_paid = shares_paid + 1,
+             status = CASE WHEN shares_paid + 1 >= COALESCE(split_ways, 1)
+                           THEN 'settled' ELSE status END,
+             closed_at = CASE WHEN shares_paid + 1 >= COALESCE(split_ways, 1)
+                              THEN NOW() ELSE closed_at END
+         WHERE id = $1",
+    )
+    .bind(tab_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// ── Commands ──
+
+/// Zones with their active tables and how many tabs are open on each.
+#[tauri::command]
+pub async fn get_table_layout(db: State<'_, Database>) -> Result<Vec<DiningZone>, String> {
+    let mut zones = sqlx::query_as::<_, DiningZone>(
+        "SELECT id, name, sort_order FROM dining_zones ORDER BY sort_order, id",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let tables = sqlx::query_as::<_, DiningTable>(
+        "SELECT dt.id, dt.zone_id, dt.name, dt.seats, dt.sort_order, dt.is_active,
+                (SELECT COUNT(*) FROM tabs t WHERE t.table_id = dt.id AND t.status = 'open')
+                    AS open_tabs
+         FROM dining_tables dt
+         WHERE dt.is_active
+         ORDER BY dt.sort_order, dt.id",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut by_zone: HashMap<i32, Vec<DiningTable>> = HashMap::new();
+    for table in tables {
+        by_zone.entry(table.zone_id).or_default().push(table);
+    }
+    for zone in &mut zones {
+        zone.tables = by_zone.remove(&zone.id).unwrap_or_default();
+    }
+
+    Ok(zones)
+}
+
+/// Creates a zone when `id` is 0, otherwise renames it. Tables are saved
+/// with `save_dining_table`.
+#[tauri::command]
+pub async fn save_dining_zone(zone: DiningZone, db: State<'_, Database>) -> Result<i32, String> {
+    if zone.name.trim().is_empty() {
+        return Err("กรุณาระบุชื่อโซน".to_string());
+    }
+
+    if zone.id == 0 {
+        let (id,): (i32,) = sqlx::query_as(
+            "INSERT INTO dining_zones (name, sort_order) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(zone.name.trim())
+        .bind(zone.sort_order)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| format!("บันทึกโซนไม่สำเร็จ: {}", e))?;
+        return Ok(id);
+    }
+
+    let rows = sqlx::query("UPDATE dining_zones SET name = $1, sort_order = $2 WHERE id = $3")
+        .bind(zone.name.trim())
+        .bind(zone.sort_order)
+        .bind(zone.id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| format!("บันทึกโซนไม่สำเร็จ: {}", e))?
+        .rows_affected();
+    if rows == 0 {
+        return Err("ไม่พบโซน".to_string());
+    }
+
+    Ok(zone.id)
+}
+
+/// Creates a table when `id` is 0, otherwise updates it. Tables are never
+/// deleted because past tabs reference them; set `is_active` to false.
+#[tauri::command]
+pub async fn save_dining_table(table: DiningTable, db: State<'_, Database>) -> Result<i32, String> {
+    if table.name.trim().is_empty() {
+        return Err("กรุณาระบุชื่อโต๊ะ".to_string());
+    }
+    if table.seats <= 0 {
+        return Err("จำนวนที่นั่งต้องมากกว่า 0".to_string());
+    }
+
+    if table.id == 0 {
+        let (id,): (i32,) = sqlx::query_as(
+            "INSERT INTO dining_tables (zone_id, name, seats, sort_order, is_active)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING id",
+        )
+        .bind(table.zone_id)
+        .bind(table.name.trim())
+        .bind(table.seats)
+        .bind(table.sort_order)
+        .bind(table.is_active)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| format!("บันทึกโต๊ะไม่สำเร็จ: {}", e))?;
+        return Ok(id);
+    }
+
+    let rows = sqlx::query(
+        "UPDATE dining_tables
+         SET zone_id = $1, name = $2, seats = $3, sort_order = $4, is_active = $5
+         WHERE id = $6",
+    )
+    .bind(table.zone_id)
+    .bind(table.name.trim())
+    .bind(table.seats)
+    .bind(table.sort_order)
+    .bind(table.is_active)
+    .bind(table.id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกโต๊ะไม่สำเร็จ: {}", e))?
+    .rows_affected();
+    if rows == 0 {
+        return Err("ไม่พบโต๊ะ".to_string());
+    }
+
+    Ok(table.id)
+}
+
+#[tauri::command]
+pub async fn open_tab(
+    table_id: i32,
+    guests: Option<i32>,
+    opened_by: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    let active: Option<(bool,)> =
+        sqlx::query_as("SELECT is_active FROM dining_tables WHERE id = $1")
+            .bind(table_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    if !matches!(active, Some((true,))) {
+        return Err("ไม่พบโต๊ะ".to_string());
+    }
+
+    let (id,): (i32,) = sqlx::query_as(
+        "INSERT INTO tabs (table_id, guests, opened_by) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(table_id)
+    .bind(guests.unwrap_or(1).max(1))
+    .bind(&opened_by)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| format!("เปิดบิลโต๊ะไม่สำเร็จ: {}", e))?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn get_open_tabs(
+    table_id: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<Vec<Tab>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    load_tabs(&mut conn, table_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Adds a round of items to a tab at the current prices and sends it to the
+/// kitchen: its lines join the kitchen queue and each station's ticket is
+/// queued for printing. Stamp reward lines are added free; their stamps are
+/// redeemed when the tab is settled, so the settling order needs the customer.
+#[tauri::command]
+pub async fn add_tab_items(
+    tab_id: i32,
+    mut items: Vec<OrderItemInput>,
+    combos: Option<Vec<ComboOrderInput>>,
+    note: Option<String>,
+    added_by: String,
+    db: State<'_, Database>,
+) -> Result<TabRound, String> {
+    if items.is_empty() && combos.as_ref().is_none_or(|c| c.is_empty()) {
+        return Err("ไม่มีรายการที่จะเพิ่ม".to_string());
+    }
+
+    let order_number = orders::next_order_number(&db.pool).await;
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    lock_unpaid_tab(&mut tx, tab_id).await?;
+    pricing::check_order_prices(&mut tx, &mut items).await?;
+
+    let offset = next_combo_index(&mut tx, tab_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut lines = combos::expand(&mut tx, combos.as_deref().unwrap_or_default()).await?;
+    for line in &mut lines {
+        line.combo_index = line.combo_index.map(|i| i + offset);
+    }
+    items.extend(lines);
+
+    let (round_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO tab_rounds (tab_id, order_number, note, added_by)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id",
+    )
+    .bind(tab_id)
+    .bind(order_number)
+    .bind(note.as_deref().map(str::trim).filter(|n| !n.is_empty()))
+    .bind(&added_by)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("เพิ่มรายการในบิลโต๊ะไม่สำเร็จ: {}", e))?;
+
+    for item in &items {
+        sqlx::query(
+            "INSERT INTO tab_items (tab_id, item_id, item_name, qty, price, sold_by, combo_id,
+                                    combo_index, is_reward, added_by, round_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(tab_id)
+        .bind(item.item_id)
+        .bind(&item.item_name)
+        .bind(item.qty)
+        .bind(if item.is_reward { 0.0 } else { item.price })
+        .bind(if item.is_weighed() {
+            item.sold_by.as_str()
+        } else {
+            "unit"
+        })
+        .bind(item.combo_id)
+        .bind(item.combo_index)
+        .bind(item.is_reward)
+        .bind(&added_by)
+        .bind(round_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("เพิ่มรายการในบิลโต๊ะไม่สำเร็จ: {}", e))?;
+    }
+
+    let mut print_jobs = tickets::queue_round_tickets(&mut tx, round_id)
+        .await
+        .map_err(|e| format!("เพิ่มงานพิมพ์ไม่สำเร็จ: {}", e))?;
+    print_jobs.extend(
+        labels::queue_round_labels(&mut tx, round_id)
+            .await
+            .map_err(|e| format!("เพิ่มงานพิมพ์ไม่สำเร็จ: {}", e))?,
+    );
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(TabRound {
+        round_id,
+        order_number,
+        print_jobs,
+    })
+}
+
+/// Removes a line from a tab, e.g. an item entered by mistake.
+#[tauri::command]
+pub async fn remove_tab_item(tab_item_id: i32, db: State<'_, Database>) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    let tab: Option<(i32, Option<i32>)> =
+        sqlx::query_as("SELECT tab_id, round_id FROM tab_items WHERE id = $1")
+            .bind(tab_item_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let Some((tab_id, round_id)) = tab else {
+        return Ok(false);
+    };
+    lock_unpaid_tab(&mut tx, tab_id).await?;
+
+    sqlx::query("DELETE FROM tab_items WHERE id = $1")
+        .bind(tab_item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // The kitchen stops showing the line, and the round once nothing is left.
+    if let Some(round_id) = round_id {
+        kitchen::sync_round_status(&mut tx, round_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Moves a tab to another table, e.g. when guests change seats.
+#[tauri::command]
+pub async fn transfer_tab(
+    tab_id: i32,
+    table_id: i32,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    lock_open_tab(&mut tx, tab_id).await?;
+
+    let active: Option<(bool,)> =
+        sqlx::query_as("SELECT is_active FROM dining_tables WHERE id = $1")
+            .bind(table_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    if !matches!(active, Some((true,))) {
+        return Err("ไม่พบโต๊ะ".to_string());
+    }
+
+    sqlx::query("UPDATE tabs SET table_id = $1 WHERE id = $2")
+        .bind(table_id)
+        .bind(tab_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("ย้ายโต๊ะไม่สำเร็จ: {}", e))?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Moves every line of `from_tab_id` into `into_tab_id` and closes the
+/// source tab, e.g. when two tables are joined.
+#[tauri::command]
+pub async fn merge_tabs(
+    from_tab_id: i32,
+    into_tab_id: i32,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    if from_tab_id == into_tab_id {
+        return Err("ต้องเลือกบิลโต๊ะอื่น".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    // Lock in id order so two merges of the same pair cannot deadlock.
+    for id in [from_tab_id.min(into_tab_id), from_tab_id.max(into_tab_id)] {
+        lock_unpaid_tab(&mut tx, id).await?;
+    }
+
+    let offset = next_combo_index(&mut tx, into_tab_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "UPDATE tab_items SET tab_id = $1, combo_index = combo_index + $2 WHERE tab_id = $3",
+    )
+    .bind(into_tab_id)
+    .bind(offset)
+    .bind(from_tab_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("รวมบิลไม่สำเร็จ: {}", e))?;
+
+    // Rounds still in the kitchen are served to the table the tab is now on.
+    sqlx::query("UPDATE tab_rounds SET tab_id = $1 WHERE tab_id = $2")
+        .bind(into_tab_id)
+        .bind(from_tab_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("รวมบิลไม่สำเร็จ: {}", e))?;
+
+    sqlx::query(
+        "UPDATE tabs
+         SET guests = guests + (SELECT guests FROM tabs WHERE id = $2)
+         WHERE id = $1",
+    )
+    .bind(into_tab_id)
+    .bind(from_tab_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "UPDATE tabs SET status = 'merged', merged_into = $1, closed_at = NOW() WHERE id = $2",
+    )
+    .bind(into_tab_id)
+    .bind(from_tab_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Splits a bill by item: the given lines (or part of their qty) move to a
+/// new tab on the same table, which is settled on its own.
+#[tauri::command]
+pub async fn split_tab(
+    tab_id: i32,
+    lines: Vec<TabSplitLine>,
+    opened_by: String,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    if lines.is_empty() {
+        return Err("กรุณาเลือกรายการที่จะแยกบิล".to_string());
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+
+    lock_unpaid_tab(&mut tx, tab_id).await?;
+
+    let (new_tab_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO tabs (table_id, guests, opened_by)
+         SELECT table_id, 1, $2 FROM tabs WHERE id = $1
+         RETURNING id",
+    )
+    .bind(tab_id)
+    .bind(&opened_by)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("แยกบิลไม่สำเร็จ: {}", e))?;
+
+    for line in &lines {
+        let current: Option<(f64, String, String)> = sqlx::query_as(
+            "SELECT qty, item_name, sold_by FROM tab_items
+             WHERE id = $1 AND tab_id = $2
+             FOR UPDATE",
+        )
+        .bind(line.tab_item_id)
+        .bind(tab_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some((qty, item_name, sold_by)) = current else {
+            return Err("ไม่พบรายการในบิลโต๊ะ".to_string());
+        };
+        if line.qty <= 0.0 || line.qty > qty {
+            return Err(format!("จำนวน {} ที่แยกต้องไม่เกิน {}", item_name, qty));
+        }
+        if sold_by == "unit" && line.qty.fract() != 0.0 {
+            return Err(format!("จำนวน {} ต้องเป็นจำนวนเต็ม", item_name));
+        }
+
+        if line.qty == qty {
+            sqlx::query("UPDATE tab_items SET tab_id = $1 WHERE id = $2")
+                .bind(new_tab_id)
+                .bind(line.tab_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("แยกบิลไม่สำเร็จ: {}", e))?;
+        } else {
+            sqlx::query("UPDATE tab_items SET qty = qty - $1 WHERE id = $2")
+                .bind(line.qty)
+                .bind(line.tab_item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("แยกบิลไม่สำเร็จ: {}", e))?;
+
+            sqlx::query(
+                "INSERT INTO tab_items (tab_id, item_id, item_name, qty, price, sold_by, combo_id,
+                                        combo_index, is_reward, added_by, added_at, round_id,
+                                        kitchen_status, preparing_at, ready_at, served_at,
+                                        split_from)
+                 SELECT $1, item_id, item_name, $2, price, sold_by, combo_id, combo_index,
+                        is_reward, added_by, added_at, round_id, kitchen_status, preparing_at,
+                        ready_at, served_at, id
+                 FROM tab_items WHERE id = $3",
+            )
+            .bind(new_tab_id)
+            .bind(line.qty)
+            .bind(line.tab_item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("แยกบิลไม่สำเร็จ: {}", e))?;
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(new_tab_id)
+}
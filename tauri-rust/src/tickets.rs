@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Utc};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+use crate::print_queue;
+use crate::printers::{self, Printer};
+
+const LINE_WIDTH: usize = 32;
+
+// ── Request / Response types ──
+
+#[derive(FromRow)]
+struct TicketHeader {
+    order_number: i32,
+    order_type: String,
+    table_name: Option<String>,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct TicketLine {
+    category_id: String,
+    item_name: String,
+    qty: f64,
+    sold_by: String,
+}
+
+// ── Helpers ──
+
+/// `2`, `0.250 kg` or `350 g`, as the line should read on a ticket or label.
+pub(crate) fn format_qty(qty: f64, sold_by: &str) -> String {
+    match sold_by {
+        "kg" => format!("{:.3} kg", qty),
+        "100g" => format!("{:.0} g", qty * 100.0),
+        _ => format!("{}", qty),
+    }
+}
+
+fn order_type_label(order_type: &str) -> &'static str {
+    match order_type {
+        "dine_in" => "ทานที่ร้าน",
+        "delivery" => "เดลิเวอรี่",
+        _ => "กลับบ้าน",
+    }
+}
+
+/// ESC/POS ticket for one station: order number in large type, then only the
+/// lines that station makes.
+fn render_ticket(printer: &Printer, header: &TicketHeader, lines: &[&TicketLine]) -> Vec<u8> {
+    let mut out = b"\x1B@".to_vec();
+    if let Some(page) = printer.thai_codepage {
+        out.extend([0x1B, b't', page as u8]);
+    }
+
+    let text = |out: &mut Vec<u8>, s: &str| out.extend(printers::encode_text(printer, s));
+
+    out.extend(b"\x1Ba\x01");
+    text(&mut out, &format!("{}\n", printer.name));
+    out.extend(b"\x1D!\x11");
+    text(&mut out, &format!("#{}\n", header.order_number));
+    out.extend(b"\x1D!\x00");
+    let place = match &header.table_name {
+        Some(table) => format!("{} - โต๊ะ {}", order_type_label(&header.order_type), table),
+        None => order_type_label(&header.order_type).to_string(),
+    };
+    text(&mut out, &format!("{}\n", place));
+    text(
+        &mut out,
+        &format!(
+            "{}\n",
+            header
+                .created_at
+                .with_timezone(&Local)
+                .format("%d/%m/%Y %H:%M")
+        ),
+    );
+
+    out.extend(b"\x1Ba\x00");
+    text(&mut out, &format!("{}\n", "-".repeat(LINE_WIDTH)));
+    out.extend(b"\x1D!\x01");
+    for line in lines {
+        text(
+            &mut out,
+            &format!(
+                "{} x {}\n",
+                format_qty(line.qty, &line.sold_by),
+                line.item_name
+            ),
+        );
+    }
+    out.extend(b"\x1D!\x00");
+    text(&mut out, &format!("{}\n", "-".repeat(LINE_WIDTH)));
+
+    if let Some(note) = header.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        text(&mut out, &format!("หมายเหตุ: {}\n", note.trim()));
+    }
+
+    out.extend(b"\n\n\n\x1DVB\x00");
+    out
+}
+
+/// Queues each station's ticket for an order. Items go to the printers
+/// routed for their category, or to every kitchen printer when the category
+/// has no route to an active kitchen or bar printer. Returns the print job
+/// ids.
+pub(crate) async fn queue_order_tickets(
+    conn: &mut PgConnection,
+    order_id: &str,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let header = sqlx::query_as::<_, TicketHeader>(
+        "SELECT o.order_number, o.order_type, dt.name AS table_name, o.note, o.created_at
+         FROM orders o
+         LEFT JOIN dining_tables dt ON dt.id = o.table_id
+         WHERE o.id = $1",
+    )
+    .bind(order_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let lines = sqlx::query_as::<_, TicketLine>(
+        "SELECT COALESCE(m.category_id, '') AS category_id, oi.item_name, oi.qty, oi.sold_by
+         FROM order_items oi
+         LEFT JOIN menu_items m ON m.id = oi.item_id
+         WHERE oi.order_id = $1
+         ORDER BY oi.id",
+    )
+    .bind(order_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    queue_tickets(conn, &header, &lines, Some(order_id)).await
+}
+
+/// Queues the tickets for a round added to a tab, routed the same way as an
+/// order's.
+pub(crate) async fn queue_round_tickets(
+    conn: &mut PgConnection,
+    round_id: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let header = sqlx::query_as::<_, TicketHeader>(
+        "SELECT r.order_number, 'dine_in' AS order_type, dt.name AS table_name, r.note,
+                r.created_at
+         FROM tab_rounds r
+         JOIN tabs t ON t.id = r.tab_id
+         JOIN dining_tables dt ON dt.id = t.table_id
+         WHERE r.id = $1",
+    )
+    .bind(round_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let lines = sqlx::query_as::<_, TicketLine>(
+        "SELECT COALESCE(m.category_id, '') AS category_id, ti.item_name, ti.qty, ti.sold_by
+         FROM tab_items ti
+         LEFT JOIN menu_items m ON m.id = ti.item_id
+         WHERE ti.round_id = $1
+         ORDER BY ti.id",
+    )
+    .bind(round_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    queue_tickets(conn, &header, &lines, None).await
+}
+
+async fn queue_tickets(
+    conn: &mut PgConnection,
+    header: &TicketHeader,
+    lines: &[TicketLine],
+    order_id: Option<&str>,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let mut job_ids = Vec::new();
+    for (printer, data) in build_tickets(conn, header, lines).await? {
+        job_ids.push(print_queue::enqueue(conn, printer.id, "ticket", order_id, &data).await?);
+    }
+
+    Ok(job_ids)
+}
+
+/// Which kitchen and bar printers make each line. A line goes to the
+/// printers routed for its category; routes to label printers or to printers
+/// not in `printers` (inactive ones) do not count, so a category with only a
+/// sticker printer still reaches every kitchen printer.
+fn route_lines<'a>(
+    printers: &[Printer],
+    routes: &HashMap<String, Vec<i32>>,
+    lines: &'a [TicketLine],
+) -> HashMap<i32, Vec<&'a TicketLine>> {
+    let is_station = |id: &i32| {
+        printers
+            .iter()
+            .any(|p| p.id == *id && (p.role == "kitchen" || p.role == "bar"))
+    };
+    let kitchen: Vec<i32> = printers
+        .iter()
+        .filter(|p| p.role == "kitchen")
+        .map(|p| p.id)
+        .collect();
+
+    let mut by_printer: HashMap<i32, Vec<&TicketLine>> = HashMap::new();
+    for line in lines {
+        let routed: Vec<i32> = routes
+            .get(&line.category_id)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(is_station)
+            .collect();
+        let targets = if routed.is_empty() { &kitchen } else { &routed };
+        for printer_id in targets {
+            by_printer.entry(*printer_id).or_default().push(line);
+        }
+    }
+
+    by_printer
+}
+
+async fn build_tickets(
+    conn: &mut PgConnection,
+    header: &TicketHeader,
+    lines: &[TicketLine],
+) -> Result<Vec<(Printer, Vec<u8>)>, sqlx::Error> {
+    let printers = printers::active_printers(conn).await?;
+    let routes = printers::category_routes(conn).await?;
+    let by_printer = route_lines(&printers, &routes, lines);
+
+    Ok(printers
+        .iter()
+        .filter_map(|printer| {
+            let lines = by_printer.get(&printer.id)?;
+            Some((printer.clone(), render_ticket(printer, header, lines)))
+        })
+        .collect())
+}
+
+// ── Commands ──
+
+/// Queues an order's kitchen and bar tickets again, e.g. after a paper jam.
+#[tauri::command]
+pub async fn reprint_kitchen_tickets(
+    order_id: String,
+    db: State<'_, Database>,
+) -> Result<Vec<i32>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    queue_order_tickets(&mut conn, &order_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Queues the tickets for a tab round again.
+#[tauri::command]
+pub async fn reprint_tab_round_tickets(
+    round_id: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<i32>, String> {
+    let mut conn = db.pool.acquire().await.map_err(|e| e.to_string())?;
+
+    queue_round_tickets(&mut conn, round_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printer(id: i32, role: &str) -> Printer {
+        Printer {
+            id,
+            name: format!("{} {}", role, id),
+            role: role.to_string(),
+            connection: "network".to_string(),
+            host: Some("192.168.1.50".to_string()),
+            port: Some(9100),
+            queue_name: None,
+            thai_codepage: None,
+            label_language: "tspl".to_string(),
+            label_width_mm: 40,
+            label_height_mm: 30,
+            is_active: true,
+        }
+    }
+
+    fn line(category_id: &str, item_name: &str) -> TicketLine {
+        TicketLine {
+            category_id: category_id.to_string(),
+            item_name: item_name.to_string(),
+            qty: 1.0,
+            sold_by: "unit".to_string(),
+        }
+    }
+
+    fn routed<'a>(by_printer: &HashMap<i32, Vec<&'a TicketLine>>, printer_id: i32) -> Vec<&'a str> {
+        by_printer
+            .get(&printer_id)
+            .map(|lines| lines.iter().map(|l| l.item_name.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn lines_follow_their_category_route() {
+        let printers = [printer(1, "kitchen"), printer(2, "bar")];
+        let routes = HashMap::from([
+            ("drinks".to_string(), vec![2]),
+            ("food".to_string(), vec![1]),
+        ]);
+        let lines = [line("drinks", "Latte"), line("food", "Toast")];
+
+        let by_printer = route_lines(&printers, &routes, &lines);
+        assert_eq!(routed(&by_printer, 1), ["Toast"]);
+        assert_eq!(routed(&by_printer, 2), ["Latte"]);
+    }
+
+    #[test]
+    fn category_can_go_to_several_stations() {
+        let printers = [printer(1, "kitchen"), printer(2, "bar")];
+        let routes = HashMap::from([("sets".to_string(), vec![1, 2])]);
+        let lines = [line("sets", "Breakfast set")];
+
+        let by_printer = route_lines(&printers, &routes, &lines);
+        assert_eq!(routed(&by_printer, 1), ["Breakfast set"]);
+        assert_eq!(routed(&by_printer, 2), ["Breakfast set"]);
+    }
+
+    #[test]
+    fn unrouted_category_goes_to_every_kitchen_printer() {
+        let printers = [
+            printer(1, "kitchen"),
+            printer(2, "bar"),
+            printer(3, "kitchen"),
+        ];
+        let lines = [line("food", "Toast")];
+
+        let by_printer = route_lines(&printers, &HashMap::new(), &lines);
+        assert_eq!(routed(&by_printer, 1), ["Toast"]);
+        assert_eq!(routed(&by_printer, 3), ["Toast"]);
+        assert!(routed(&by_printer, 2).is_empty());
+    }
+
+    #[test]
+    fn label_only_route_falls_back_to_kitchen() {
+        let printers = [printer(1, "kitchen"), printer(4, "label")];
+        let routes = HashMap::from([("drinks".to_string(), vec![4])]);
+        let lines = [line("drinks", "Latte")];
+
+        let by_printer = route_lines(&printers, &routes, &lines);
+        assert_eq!(routed(&by_printer, 1), ["Latte"]);
+        assert!(!by_printer.contains_key(&4));
+    }
+
+    #[test]
+    fn route_to_inactive_printer_falls_back_to_kitchen() {
+        // Printer 2 is not in the active list.
+        let printers = [printer(1, "kitchen")];
+        let routes = HashMap::from([("drinks".to_string(), vec![2])]);
+        let lines = [line("drinks", "Latte")];
+
+        let by_printer = route_lines(&printers, &routes, &lines);
+        assert_eq!(routed(&by_printer, 1), ["Latte"]);
+        assert!(!by_printer.contains_key(&2));
+    }
+
+    #[test]
+    fn ticket_text_uses_the_thai_code_page() {
+        let mut bar = printer(2, "bar");
+        bar.thai_codepage = Some(21);
+        let header = TicketHeader {
+            order_number: 42,
+            order_type: "dine_in".to_string(),
+            table_name: Some("A1".to_string()),
+            note: None,
+            created_at: Utc::now(),
+        };
+        let latte = line("drinks", "ลาเต้");
+
+        let out = render_ticket(&bar, &header, &[&latte]);
+        assert!(out.starts_with(&[0x1B, b'@', 0x1B, b't', 21]));
+        // ลาเต้ in TIS-620
+        assert!(out.windows(5).any(|w| w == [0xC5, 0xD2, 0xE0, 0xB5, 0xE9]));
+        assert!(!out.windows(3).any(|w| w == "ล".as_bytes()));
+        assert!(out.windows(3).any(|w| w == b"#42"));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use tauri::State;
+
+use crate::database::Database;
+
+/// Thai is stored on the rows themselves and is the fallback for every locale.
+pub const DEFAULT_LOCALE: &str = "th";
+
+// ── Request / Response types ──
+
+/// Name and description of a category or menu item in one locale.
+/// `entity_type` is `category` or `menu_item`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Translation {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub locale: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// ── Helpers ──
+
+fn normalize_locale(locale: &str) -> Result<String, String> {
+    let locale = locale.trim().to_lowercase().replace('_', "-");
+    if locale.is_empty()
+        || locale.len() > 10
+        || !locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("รหัสภาษา '{}' ไม่ถูกต้อง", locale));
+    }
+
+    Ok(locale)
+}
+
+/// Loads the translations of one entity type for `locale`, keyed by entity
+/// id. Returns `None` for Thai (or no locale), where the stored names are used
+/// as they are.
+pub(crate) async fn for_locale(
+    conn: &mut PgConnection,
+    entity_type: &str,
+    locale: Option<&str>,
+) -> Result<Option<HashMap<String, Translation>>, String> {
+    let locale = match locale.map(normalize_locale).transpose()? {
+        Some(locale) if locale != DEFAULT_LOCALE => locale,
+        _ => return Ok(None),
+    };
+
+    let rows = sqlx::query_as::<_, Translation>(
+        "SELECT entity_type, entity_id, locale, name, description
+         FROM translations
+         WHERE entity_type = $1 AND locale = $2",
+    )
+    .bind(entity_type)
+    .bind(&locale)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(
+        rows.into_iter().map(|t| (t.entity_id.clone(), t)).collect(),
+    ))
+}
+
+// ── Commands ──
+
+#[tauri::command]
+pub async fn get_translations(
+    entity_type: String,
+    entity_id: String,
+    db: State<'_, Database>,
+) -> Result<Vec<Translation>, String> {
+    sqlx::query_as::<_, Translation>(
+        "SELECT entity_type, entity_id, locale, name, description
+         FROM translations
+         WHERE entity_type = $1 AND entity_id = $2
+         ORDER BY locale",
+    )
+    .bind(&entity_type)
+    .bind(&entity_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_translation(
+    translation: Translation,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let locale = normalize_locale(&translation.locale)?;
+    if locale == DEFAULT_LOCALE {
+        return Err("ชื่อภาษาไทยแก้ไขที่ข้อมูลเมนูโดยตรง".to_string());
+    }
+    if translation.name.trim().is_empty() {
+        return Err("กรุณาระบุชื่อ".to_string());
+    }
+
+    let exists: Option<(i32,)> = match translation.entity_type.as_str() {
+        "category" => sqlx::query_as("SELECT 1 FROM categories WHERE id = $1")
+            .bind(&translation.entity_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?,
+        "menu_item" => sqlx::query_as("SELECT 1 FROM menu_items WHERE id::text = $1")
+            .bind(&translation.entity_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| e.to_string())?,
+        _ => return Err("ประเภทต้องเป็น category หรือ menu_item".to_string()),
+    };
+    if exists.is_none() {
+        return Err("ไม่พบรายการที่จะแปล".to_string());
+    }
+
+    sqlx::query(
+        "INSERT INTO translations (entity_type, entity_id, locale, name, description)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (entity_type, entity_id, locale)
+         DO UPDATE SET name = EXCLUDED.name, description = EXCLUDED.description",
+    )
+    .bind(&translation.entity_type)
+    .bind(&translation.entity_id)
+    .bind(&locale)
+    .bind(translation.name.trim())
+    .bind(&translation.description)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| format!("บันทึกคำแปลไม่สำเร็จ: {}", e))?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn delete_translation(
+    entity_type: String,
+    entity_id: String,
+    locale: String,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let rows = sqlx::query(
+        "DELETE FROM translations WHERE entity_type = $1 AND entity_id = $2 AND locale = $3",
+    )
+    .bind(&entity_type)
+    .bind(&entity_id)
+    .bind(normalize_locale(&locale)?)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    Ok(rows > 0)
+}